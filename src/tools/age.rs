@@ -0,0 +1,682 @@
+use crate::tools::hash::Hash;
+use crate::tools::kdf::KdfParams;
+use crate::tools::random::SafeRandom;
+use crate::tools::secret::AeadAlgorithm;
+use crate::tools::stream::{DecryptStream, EncryptStream};
+use bech32::{FromBase32, ToBase32, Variant};
+use data_encoding::{BASE64, BASE64_NOPAD};
+use hmac::Hmac;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use wasm_bindgen::prelude::*;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const AGE_VERSION_LINE: &str = "age-encryption.org/v1";
+const X25519_INFO: &[u8] = b"age-encryption.org/v1/X25519";
+const SCRYPT_SALT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+const WRAP_ALGORITHM: AeadAlgorithm = AeadAlgorithm::ChaCha20Poly1305;
+const FILE_KEY_LEN: usize = 16;
+const WRAPPED_KEY_LEN: usize = FILE_KEY_LEN + 16;
+const PAYLOAD_NONCE_LEN: usize = 16;
+const CHUNK_SIZE: usize = 64 * 1024;
+const COLUMNS: usize = 64;
+const SCRYPT_SALT_LEN: usize = 16;
+const DEFAULT_SCRYPT_LOG_N: u8 = 18;
+/// highest work factor accepted when decrypting, so a file cannot demand unbounded memory
+const MAX_SCRYPT_LOG_N: u8 = 20;
+const RECIPIENT_HRP: &str = "age";
+const IDENTITY_HRP: &str = "age-secret-key-";
+const ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+const ARMOR_END: &str = "-----END AGE ENCRYPTED FILE-----";
+
+/// `Stanza` one `-> type args...` recipient block of the header
+struct Stanza {
+    tag: String,
+    args: Vec<String>,
+    body: Vec<u8>,
+}
+
+impl Stanza {
+    /// `encode` arguments line plus the body wrapped at 64 columns; the last line is always short
+    fn encode(&self) -> String {
+        let mut out = format!("-> {}", self.tag);
+        for arg in &self.args {
+            out.push(' ');
+            out.push_str(arg);
+        }
+        out.push('\n');
+        let body = BASE64_NOPAD.encode(&self.body);
+        let mut rest = body.as_str();
+        while rest.len() >= COLUMNS {
+            out.push_str(&rest[..COLUMNS]);
+            out.push('\n');
+            rest = &rest[COLUMNS..];
+        }
+        out.push_str(rest);
+        out.push('\n');
+        out
+    }
+}
+
+/// `AgeHeader` parsed header with the byte ranges needed to check the MAC
+struct AgeHeader {
+    stanzas: Vec<Stanza>,
+    mac_input_len: usize,
+    mac: Vec<u8>,
+    payload_start: usize,
+}
+
+/// `read_line` next `\n`-terminated ASCII line starting at `pos`
+fn read_line(data: &[u8], pos: usize) -> Result<(&str, usize), String> {
+    let rest = data.get(pos..).unwrap_or_default();
+    let end = rest
+        .iter()
+        .position(|b| *b == b'\n')
+        .ok_or("error: truncated age header")?;
+    let line = std::str::from_utf8(&rest[..end])
+        .ok()
+        .filter(|line| line.bytes().all(|b| (0x20..0x7f).contains(&b)))
+        .ok_or("error: invalid characters in age header")?;
+    Ok((line, pos + end + 1))
+}
+
+fn decode_b64(text: &str) -> Result<Vec<u8>, String> {
+    BASE64_NOPAD
+        .decode(text.as_bytes())
+        .map_err(|_| "error: invalid base64 in age header".into())
+}
+
+fn parse_header(data: &[u8]) -> Result<AgeHeader, String> {
+    let (version, mut pos) = read_line(data, 0)?;
+    if version != AGE_VERSION_LINE {
+        return Err("error: unsupported age version".into());
+    }
+    let mut stanzas = Vec::new();
+    loop {
+        let start = pos;
+        let (line, next) = read_line(data, pos)?;
+        pos = next;
+        if let Some(mac) = line.strip_prefix("--- ") {
+            let mac = decode_b64(mac)?;
+            if mac.len() != 32 {
+                return Err("error: invalid age header MAC".into());
+            }
+            return Ok(AgeHeader {
+                stanzas,
+                mac_input_len: start + 3,
+                mac,
+                payload_start: pos,
+            });
+        }
+        let line = line
+            .strip_prefix("-> ")
+            .ok_or("error: invalid age header line")?;
+        let mut args: Vec<String> = line.split(' ').map(String::from).collect();
+        if args.iter().any(|arg| arg.is_empty()) {
+            return Err("error: invalid age stanza arguments".into());
+        }
+        let tag = args.remove(0);
+
+        let mut body = String::new();
+        loop {
+            let (line, next) = read_line(data, pos)?;
+            pos = next;
+            if line.len() > COLUMNS {
+                return Err("error: age stanza line is too long".into());
+            }
+            body.push_str(line);
+            if line.len() < COLUMNS {
+                break;
+            }
+        }
+        stanzas.push(Stanza {
+            tag,
+            args,
+            body: decode_b64(&body)?,
+        });
+    }
+}
+
+/// `header_mac` HMAC-SHA256 over the header text up to and including `---`
+fn header_mac(file_key: &[u8], header: &[u8]) -> Result<Vec<u8>, String> {
+    let key = Hash::gen_hkdf(file_key, b"", b"header", 32).ok_or("error: key derivation failed")?;
+    Ok(Hash::compute_hmac::<Hmac<Sha256>>(&key, header))
+}
+
+fn wrap_file_key(key: &[u8], file_key: &[u8]) -> Result<Vec<u8>, String> {
+    WRAP_ALGORITHM.seal(key, &[0u8; 12], file_key, b"")
+}
+
+fn unwrap_file_key(key: &[u8], body: &[u8]) -> Option<Vec<u8>> {
+    if body.len() != WRAPPED_KEY_LEN {
+        return None;
+    }
+    WRAP_ALGORITHM.open(key, &[0u8; 12], body, b"").ok()
+}
+
+fn parse_recipient(recipient: &str) -> Result<PublicKey, String> {
+    let (hrp, data, variant) = bech32::decode(recipient.trim())
+        .map_err(|_| format!("error: invalid age recipient: {}", recipient))?;
+    let bytes = Vec::<u8>::from_base32(&data)
+        .map_err(|_| format!("error: invalid age recipient: {}", recipient))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .ok()
+        .filter(|_| hrp == RECIPIENT_HRP && variant == Variant::Bech32)
+        .ok_or_else(|| format!("error: invalid age recipient: {}", recipient))?;
+    Ok(PublicKey::from(bytes))
+}
+
+fn x25519_stanza(recipient: &PublicKey, file_key: &[u8]) -> Result<Stanza, String> {
+    let bytes: [u8; 32] = SafeRandom::gen_bytes(32)
+        .raw()
+        .try_into()
+        .expect("32 random bytes");
+    let ephemeral = StaticSecret::from(bytes);
+    let share = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(recipient);
+    if !shared.was_contributory() {
+        return Err("error: invalid age recipient".into());
+    }
+    let mut salt = share.as_bytes().to_vec();
+    salt.extend_from_slice(recipient.as_bytes());
+    let key = Hash::gen_hkdf(shared.as_bytes(), &salt, X25519_INFO, 32)
+        .ok_or("error: key derivation failed")?;
+    Ok(Stanza {
+        tag: "X25519".into(),
+        args: vec![BASE64_NOPAD.encode(share.as_bytes())],
+        body: wrap_file_key(&key, file_key)?,
+    })
+}
+
+fn scrypt_key(passphrase: &str, salt: &[u8], log_n: u8) -> Result<Vec<u8>, String> {
+    let mut label = SCRYPT_SALT_LABEL.to_vec();
+    label.extend_from_slice(salt);
+    KdfParams::try_scrypt(log_n, 8, 1)?.derive(passphrase.as_bytes(), &label, 32)
+}
+
+/// `encrypt_payload` header with MAC, then the payload nonce and STREAM chunks
+fn encrypt_payload(
+    stanzas: &[Stanza],
+    file_key: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, String> {
+    let mut header = format!("{}\n", AGE_VERSION_LINE);
+    for stanza in stanzas {
+        header.push_str(&stanza.encode());
+    }
+    header.push_str("---");
+    let mac = header_mac(file_key, header.as_bytes())?;
+    header.push_str(&format!(" {}\n", BASE64_NOPAD.encode(&mac)));
+
+    let nonce = SafeRandom::gen_bytes(PAYLOAD_NONCE_LEN).raw();
+    let key =
+        Hash::gen_hkdf(file_key, &nonce, b"payload", 32).ok_or("error: key derivation failed")?;
+    let mut stream = EncryptStream::with_payload_key(key, CHUNK_SIZE);
+    let mut out = header.into_bytes();
+    out.extend_from_slice(&nonce);
+    out.extend(stream.try_update(plaintext)?);
+    out.extend(stream.try_finalize()?);
+    Ok(out)
+}
+
+/// `decrypt_payload` unwrap the file key from the stanzas, check the MAC and open the payload
+fn decrypt_payload<F>(data: &[u8], unwrap: F) -> Result<Vec<u8>, String>
+where
+    F: Fn(&[Stanza]) -> Result<Vec<u8>, String>,
+{
+    let armored;
+    let data = if Age::is_armored(data) {
+        armored = Age::try_dearmor(data)?;
+        armored.as_slice()
+    } else {
+        data
+    };
+    let header = parse_header(data)?;
+    let file_key = unwrap(&header.stanzas)?;
+    let mac = header_mac(&file_key, &data[..header.mac_input_len])?;
+    if !bool::from(mac.ct_eq(&header.mac)) {
+        return Err("error: age header MAC mismatch".into());
+    }
+
+    let payload = &data[header.payload_start..];
+    if payload.len() < PAYLOAD_NONCE_LEN {
+        return Err("error: truncated age payload".into());
+    }
+    let (nonce, chunks) = payload.split_at(PAYLOAD_NONCE_LEN);
+    let key =
+        Hash::gen_hkdf(&file_key, nonce, b"payload", 32).ok_or("error: key derivation failed")?;
+    let mut stream = DecryptStream::with_payload_key(key, CHUNK_SIZE);
+    let mut out = stream.try_update(chunks)?;
+    out.extend(stream.try_finalize()?);
+    Ok(out)
+}
+
+#[wasm_bindgen]
+/// `AgeIdentity` X25519 age identity (`AGE-SECRET-KEY-1...`)
+pub struct AgeIdentity {
+    secret: [u8; 32],
+}
+
+#[wasm_bindgen]
+impl AgeIdentity {
+    /// `generate` new random identity
+    pub fn generate() -> AgeIdentity {
+        let secret = SafeRandom::gen_bytes(32)
+            .raw()
+            .try_into()
+            .expect("32 random bytes");
+        AgeIdentity { secret }
+    }
+
+    /// `try_parse` decode an `AGE-SECRET-KEY-1...` string
+    pub(crate) fn try_parse(identity: &str) -> Result<AgeIdentity, String> {
+        let (hrp, data, variant) =
+            bech32::decode(identity.trim()).map_err(|_| "error: invalid age identity")?;
+        if hrp != IDENTITY_HRP || variant != Variant::Bech32 {
+            return Err("error: invalid age identity".into());
+        }
+        let secret = Vec::<u8>::from_base32(&data)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or("error: invalid age identity")?;
+        Ok(AgeIdentity { secret })
+    }
+
+    /// `parse` decode an `AGE-SECRET-KEY-1...` string
+    pub fn parse(identity: &str) -> Result<AgeIdentity, JsValue> {
+        Self::try_parse(identity).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `secret_key` identity encoded as `AGE-SECRET-KEY-1...`
+    pub fn secret_key(&self) -> String {
+        bech32::encode(IDENTITY_HRP, self.secret.to_base32(), Variant::Bech32)
+            .expect("valid bech32 prefix")
+            .to_uppercase()
+    }
+
+    /// `recipient` public `age1...` recipient to share with senders
+    pub fn recipient(&self) -> String {
+        let public = PublicKey::from(&StaticSecret::from(self.secret));
+        bech32::encode(
+            RECIPIENT_HRP,
+            public.as_bytes().to_base32(),
+            Variant::Bech32,
+        )
+        .expect("valid bech32 prefix")
+    }
+
+    /// `unwrap` try every X25519 stanza against this identity
+    fn unwrap(&self, stanzas: &[Stanza]) -> Option<Vec<u8>> {
+        let secret = StaticSecret::from(self.secret);
+        let public = PublicKey::from(&secret);
+        for stanza in stanzas.iter().filter(|s| s.tag == "X25519") {
+            let [share] = stanza.args.as_slice() else {
+                continue;
+            };
+            let Some(share) = BASE64_NOPAD
+                .decode(share.as_bytes())
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            else {
+                continue;
+            };
+            let share = PublicKey::from(share);
+            let shared = secret.diffie_hellman(&share);
+            if !shared.was_contributory() {
+                continue;
+            }
+            let mut salt = share.as_bytes().to_vec();
+            salt.extend_from_slice(public.as_bytes());
+            let key = Hash::gen_hkdf(shared.as_bytes(), &salt, X25519_INFO, 32)?;
+            if let Some(file_key) = unwrap_file_key(&key, &stanza.body) {
+                return Some(file_key);
+            }
+        }
+        None
+    }
+}
+
+#[wasm_bindgen]
+/// `Age` age v1 file encryption (age-encryption.org/v1), compatible with the `age` and `rage` CLIs
+pub struct Age {}
+
+#[wasm_bindgen]
+impl Age {
+    /// `try_encrypt` encrypt to X25519 recipients
+    pub(crate) fn try_encrypt(
+        recipients: &[String],
+        plaintext: &[u8],
+        armor: bool,
+    ) -> Result<Vec<u8>, String> {
+        if recipients.is_empty() {
+            return Err("error: at least one recipient is required".into());
+        }
+        let file_key = SafeRandom::gen_bytes(FILE_KEY_LEN).raw();
+        let stanzas = recipients
+            .iter()
+            .map(|r| x25519_stanza(&parse_recipient(r)?, &file_key))
+            .collect::<Result<Vec<Stanza>, String>>()?;
+        let out = encrypt_payload(&stanzas, &file_key, plaintext)?;
+        Ok(if armor {
+            Self::armor(&out).into_bytes()
+        } else {
+            out
+        })
+    }
+
+    /// `try_encrypt_with_passphrase` encrypt to a scrypt passphrase recipient
+    pub(crate) fn try_encrypt_with_passphrase(
+        passphrase: &str,
+        plaintext: &[u8],
+        log_n: u8,
+        armor: bool,
+    ) -> Result<Vec<u8>, String> {
+        if passphrase.is_empty() {
+            return Err("error: passphrase must not be empty".into());
+        }
+        if log_n > MAX_SCRYPT_LOG_N {
+            return Err(format!(
+                "error: work factor must be at most {}",
+                MAX_SCRYPT_LOG_N
+            ));
+        }
+        let file_key = SafeRandom::gen_bytes(FILE_KEY_LEN).raw();
+        let salt = SafeRandom::gen_bytes(SCRYPT_SALT_LEN).raw();
+        let key = scrypt_key(passphrase, &salt, log_n)?;
+        let stanza = Stanza {
+            tag: "scrypt".into(),
+            args: vec![BASE64_NOPAD.encode(&salt), log_n.to_string()],
+            body: wrap_file_key(&key, &file_key)?,
+        };
+        let out = encrypt_payload(&[stanza], &file_key, plaintext)?;
+        Ok(if armor {
+            Self::armor(&out).into_bytes()
+        } else {
+            out
+        })
+    }
+
+    /// `try_decrypt` decrypt with X25519 identities
+    pub(crate) fn try_decrypt(identities: &[String], data: &[u8]) -> Result<Vec<u8>, String> {
+        let identities = identities
+            .iter()
+            .map(|i| AgeIdentity::try_parse(i))
+            .collect::<Result<Vec<AgeIdentity>, String>>()?;
+        decrypt_payload(data, |stanzas| {
+            if stanzas.iter().any(|s| s.tag == "scrypt") {
+                return Err("error: file is encrypted with a passphrase".into());
+            }
+            identities
+                .iter()
+                .find_map(|identity| identity.unwrap(stanzas))
+                .ok_or_else(|| "error: no matching identity".into())
+        })
+    }
+
+    /// `try_decrypt_with_passphrase` decrypt a scrypt-encrypted file
+    pub(crate) fn try_decrypt_with_passphrase(
+        passphrase: &str,
+        data: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        decrypt_payload(data, |stanzas| {
+            // a scrypt stanza must be the only one, or another recipient could read the file
+            let [stanza] = stanzas else {
+                return Err("error: file is not encrypted with a passphrase".into());
+            };
+            let [salt, log_n] = stanza.args.as_slice() else {
+                return Err("error: file is not encrypted with a passphrase".into());
+            };
+            if stanza.tag != "scrypt" {
+                return Err("error: file is not encrypted with a passphrase".into());
+            }
+            let salt = decode_b64(salt)?;
+            if salt.len() != SCRYPT_SALT_LEN {
+                return Err("error: invalid scrypt salt".into());
+            }
+            if log_n.starts_with('0') {
+                return Err("error: invalid scrypt work factor".into());
+            }
+            let log_n: u8 = log_n
+                .parse()
+                .map_err(|_| "error: invalid scrypt work factor")?;
+            if log_n > MAX_SCRYPT_LOG_N {
+                return Err(format!("error: scrypt work factor {} is too high", log_n));
+            }
+            let key = scrypt_key(passphrase, &salt, log_n)?;
+            unwrap_file_key(&key, &stanza.body).ok_or_else(|| "error: incorrect passphrase".into())
+        })
+    }
+
+    /// `try_dearmor` decode a `BEGIN AGE ENCRYPTED FILE` block
+    pub(crate) fn try_dearmor(data: &[u8]) -> Result<Vec<u8>, String> {
+        let text = std::str::from_utf8(data).map_err(|_| "error: invalid age armor")?;
+        let lines: Vec<&str> = text
+            .trim()
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .collect();
+        let [ARMOR_BEGIN, body @ .., ARMOR_END] = lines.as_slice() else {
+            return Err("error: invalid age armor".into());
+        };
+        if let Some((last, full)) = body.split_last()
+            && (last.len() > COLUMNS || full.iter().any(|line| line.len() != COLUMNS))
+        {
+            return Err("error: invalid age armor line length".into());
+        }
+        BASE64
+            .decode(body.concat().as_bytes())
+            .map_err(|_| "error: invalid base64 in age armor".into())
+    }
+
+    /// `encrypt` encrypt to one or more X25519 recipients
+    ///
+    /// ### Arguments
+    /// * `recipients` - `age1...` public keys
+    /// * `plaintext` - File contents
+    /// * `armor` - Wrap the output in `-----BEGIN AGE ENCRYPTED FILE-----` ASCII armor
+    ///
+    /// ### Returns
+    /// age file bytes, readable by `age -d -i key.txt`
+    pub fn encrypt(
+        recipients: Vec<String>,
+        plaintext: &[u8],
+        armor: bool,
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::try_encrypt(&recipients, plaintext, armor).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `encrypt_with_passphrase` encrypt with a passphrase, like `age -p`
+    ///
+    /// ### Arguments
+    /// * `passphrase` - Passphrase
+    /// * `plaintext` - File contents
+    /// * `log_n` - scrypt work factor, at most 20 (`age` uses 18)
+    /// * `armor` - Wrap the output in ASCII armor
+    pub fn encrypt_with_passphrase(
+        passphrase: &str,
+        plaintext: &[u8],
+        log_n: u8,
+        armor: bool,
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::try_encrypt_with_passphrase(passphrase, plaintext, log_n, armor)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `encrypt_with_passphrase_default` passphrase encryption with the `age` work factor (2^18)
+    pub fn encrypt_with_passphrase_default(
+        passphrase: &str,
+        plaintext: &[u8],
+        armor: bool,
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::encrypt_with_passphrase(passphrase, plaintext, DEFAULT_SCRYPT_LOG_N, armor)
+    }
+
+    /// `decrypt` decrypt a binary or armored file with `AGE-SECRET-KEY-1...` identities
+    pub fn decrypt(identities: Vec<String>, data: &[u8]) -> Result<Vec<u8>, JsValue> {
+        Self::try_decrypt(&identities, data).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `decrypt_with_passphrase` decrypt a binary or armored passphrase-encrypted file
+    pub fn decrypt_with_passphrase(passphrase: &str, data: &[u8]) -> Result<Vec<u8>, JsValue> {
+        Self::try_decrypt_with_passphrase(passphrase, data).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `is_armored` whether the data starts with the age armor line
+    pub fn is_armored(data: &[u8]) -> bool {
+        data.trim_ascii_start().starts_with(ARMOR_BEGIN.as_bytes())
+    }
+
+    /// `armor` wrap binary age data in ASCII armor
+    pub fn armor(data: &[u8]) -> String {
+        let encoded = BASE64.encode(data);
+        let mut out = format!("{}\n", ARMOR_BEGIN);
+        for line in encoded.as_bytes().chunks(COLUMNS) {
+            out.push_str(std::str::from_utf8(line).expect("base64 is ascii"));
+            out.push('\n');
+        }
+        out.push_str(ARMOR_END);
+        out.push('\n');
+        out
+    }
+
+    /// `dearmor` decode ASCII armor back to binary age data
+    pub fn dearmor(data: &[u8]) -> Result<Vec<u8>, JsValue> {
+        Self::try_dearmor(data).map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[cfg(test)]
+fn from_hex(hex: &str) -> Vec<u8> {
+    data_encoding::HEXLOWER.decode(hex.as_bytes()).unwrap()
+}
+
+#[test]
+fn age_identity_test() {
+    let identity = AgeIdentity::try_parse(
+        "AGE-SECRET-KEY-1704JQTRW3U3934K5J6HDKLQAKQJXH8M6DEJNX6DKQWH6S4X3W65S35WJWS",
+    )
+    .unwrap();
+    assert_eq!(
+        "age1j6tnwrmxyla3prs36xrjdylrgelhrd2n0pp47s68ckjzu2zvevpqnxm5n4",
+        identity.recipient()
+    );
+    assert_eq!(
+        "AGE-SECRET-KEY-1704JQTRW3U3934K5J6HDKLQAKQJXH8M6DEJNX6DKQWH6S4X3W65S35WJWS",
+        identity.secret_key()
+    );
+
+    let generated = AgeIdentity::generate();
+    println!("identity: {}", generated.secret_key());
+    println!("recipient: {}", generated.recipient());
+    assert!(generated.recipient().starts_with("age1"));
+    let parsed = AgeIdentity::try_parse(&generated.secret_key()).unwrap();
+    assert_eq!(generated.recipient(), parsed.recipient());
+
+    assert!(AgeIdentity::try_parse(&generated.recipient()).is_err());
+    assert!(AgeIdentity::try_parse("AGE-SECRET-KEY-1QQQQ").is_err());
+    assert!(parse_recipient(&generated.secret_key()).is_err());
+    assert!(parse_recipient("age1qqqq").is_err());
+}
+
+#[test]
+fn age_reference_test() {
+    // files written by the reference `age` implementation, including grease stanzas
+    let identity =
+        "AGE-SECRET-KEY-1704JQTRW3U3934K5J6HDKLQAKQJXH8M6DEJNX6DKQWH6S4X3W65S35WJWS".to_string();
+    let binary = from_hex(
+        "6167652d656e6372797074696f6e2e6f72672f76310a2d3e20583235353139202f386b576779424f6a56636545536344694e6b6e7a33474a58706d34646c376130514165717369433844550a675254764e77352b5262775044676a312f4f334a636e736c6577584c32756c56422b344b4c6870506970550a2d3e20654a636a3e26472d67726561736520684b4d7725317b0a62465a5073726d6574724145564a6c4d2f6b2b5a4474422b4734566b6975483646475a4e49642b3758356c486c7359530a2d2d2d206e59482f372f48694939794d6d55764275436f566f696866316b6c6d7342483055766e4b30696f6f5752380a4b579ccc5cfd1b887fd95743e3d6aac2587060d600a757351f282a36b330b4040515f05294039ba81dfbe6a2420f",
+    );
+    let plaintext = Age::try_decrypt(std::slice::from_ref(&identity), &binary).unwrap();
+    assert_eq!(b"hello from age", plaintext.as_slice());
+
+    let armored = "-----BEGIN AGE ENCRYPTED FILE-----
+YWdlLWVuY3J5cHRpb24ub3JnL3YxCi0+IFgyNTUxOSBIdHduenk1UHlhUWtzaUJQ
+ZTNqKzVYemJySG5STHpkWlQrc25pdEc0bnhnCjRPemZzdnZzelcwK0ZBV0l2NUlO
+dWVuRzlYNzBkVkNFcHdzd3Vjb0FVb3cKLT4gQy1ncmVhc2UgMVNSPkkzLyB8YH5m
+WyJKCjlZa1dlRFBTZWY3Q0pma3FQVjU1Wm1xYnorampadWo4VmlGMGwya0ZQMVZk
+cENHZnZ3dWlxN2hDVHMzZWRIZE4KS1o4YkV4ak8vL0VTbEdQS2JIcwotLS0gaUJ2
+SlZjakNRYnpkM241WHRmUzh1a24zalY0M2cwTllTSUkvc0ZNaFRScwrEymD4lkaN
+wJsN/z7gTc7Htt+/RReeD12V7ksnlGA1Kvd2G1Ro0ZhJHHNt8Zw=
+-----END AGE ENCRYPTED FILE-----
+";
+    assert!(Age::is_armored(armored.as_bytes()));
+    let plaintext = Age::try_decrypt(std::slice::from_ref(&identity), armored.as_bytes()).unwrap();
+    assert_eq!(b"armored hello", plaintext.as_slice());
+
+    let passphrase = from_hex(
+        "6167652d656e6372797074696f6e2e6f72672f76310a2d3e20736372797074206d70446d2b6565582f465069776770646846756356412031300a375549724c4250527864554e53335074726a524f386451793745326837757936556957452f712f635574730a2d2d2d2065626c51514b4f447a734878532b6a4c336832554471656e684979393653656d66546271776771656e456b0a384f4a3f967ae0ce75f3d1998571b8f27aa642177bec50dde0ae598feea5153d400b198fa8ea06b457c44a65f8025cb9",
+    );
+    let plaintext = Age::try_decrypt_with_passphrase("correct horse", &passphrase).unwrap();
+    assert_eq!(b"passphrase hello", plaintext.as_slice());
+    assert!(Age::try_decrypt_with_passphrase("wrong horse", &passphrase).is_err());
+    assert!(Age::try_decrypt(std::slice::from_ref(&identity), &passphrase).is_err());
+
+    // any change to the header breaks the MAC
+    let mut tampered = binary.clone();
+    tampered[30] ^= 1;
+    assert!(Age::try_decrypt(std::slice::from_ref(&identity), &tampered).is_err());
+    let mut tampered = binary.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert!(Age::try_decrypt(&[identity], &tampered).is_err());
+
+    let other = AgeIdentity::generate().secret_key();
+    assert!(Age::try_decrypt(&[other], &binary).is_err());
+}
+
+#[test]
+fn age_roundtrip_test() {
+    let alice = AgeIdentity::generate();
+    let bob = AgeIdentity::generate();
+    let recipients = vec![alice.recipient(), bob.recipient()];
+    let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 100).map(|i| (i % 251) as u8).collect();
+
+    for len in [0, 1, CHUNK_SIZE, CHUNK_SIZE + 1, data.len()] {
+        let sealed = Age::try_encrypt(&recipients, &data[..len], false).unwrap();
+        let chunks = len.div_ceil(CHUNK_SIZE).max(1);
+        let header = parse_header(&sealed).unwrap();
+        assert_eq!(2, header.stanzas.len());
+        assert_eq!(
+            header.payload_start + PAYLOAD_NONCE_LEN + len + chunks * 16,
+            sealed.len()
+        );
+        assert_eq!(
+            &data[..len],
+            Age::try_decrypt(&[bob.secret_key()], &sealed).unwrap()
+        );
+    }
+
+    let sealed = Age::try_encrypt(&[alice.recipient()], b"hello from rtools", true).unwrap();
+    let text = String::from_utf8(sealed.clone()).unwrap();
+    println!("{}", text);
+    println!("identity: {}", alice.secret_key());
+    assert!(text.starts_with(ARMOR_BEGIN));
+    assert!(
+        text.lines()
+            .all(|line| line.len() <= COLUMNS || line == ARMOR_BEGIN)
+    );
+    assert_eq!(
+        b"hello from rtools".to_vec(),
+        Age::try_decrypt(&[bob.secret_key(), alice.secret_key()], &sealed).unwrap()
+    );
+    assert!(Age::try_decrypt(&[bob.secret_key()], &sealed).is_err());
+
+    let sealed = Age::try_encrypt_with_passphrase("hunter2", b"by passphrase", 10, false).unwrap();
+    println!("passphrase: {}", data_encoding::HEXLOWER.encode(&sealed));
+    assert_eq!(
+        b"by passphrase".to_vec(),
+        Age::try_decrypt_with_passphrase("hunter2", &sealed).unwrap()
+    );
+    assert!(Age::try_encrypt_with_passphrase("", b"x", 10, false).is_err());
+    assert!(Age::try_encrypt_with_passphrase("p", b"x", 30, false).is_err());
+    assert!(Age::try_encrypt(&[], b"x", false).is_err());
+    assert!(Age::try_encrypt(&["age1qqqq".to_string()], b"x", false).is_err());
+
+    let binary = Age::try_encrypt(&[alice.recipient()], b"x", false).unwrap();
+    assert_eq!(
+        binary,
+        Age::try_dearmor(Age::armor(&binary).as_bytes()).unwrap()
+    );
+    assert!(Age::try_dearmor(b"-----BEGIN AGE ENCRYPTED FILE-----\n").is_err());
+}
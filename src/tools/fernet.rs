@@ -0,0 +1,199 @@
+use crate::tools::hash::Hash;
+use crate::tools::parse::DataEncoding;
+use crate::tools::random::SafeRandom;
+use aes_gcm::aes::Aes128;
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit, block_padding::Pkcs7};
+use hmac::Hmac;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use wasm_bindgen::prelude::*;
+
+const FERNET_VERSION: u8 = 0x80;
+const KEY_LEN: usize = 32;
+const IV_LEN: usize = 16;
+const HMAC_LEN: usize = 32;
+const BLOCK_LEN: usize = 16;
+// version || timestamp || iv
+const HEADER_LEN: usize = 1 + 8 + IV_LEN;
+// tokens dated this far in the future are still accepted, as in Python cryptography
+const MAX_CLOCK_SKEW: u64 = 60;
+
+/// `FernetToken` verified token contents
+struct FernetToken {
+    timestamp: u64,
+    plaintext: Vec<u8>,
+}
+
+/// `decode_key` split a base64url Fernet key into signing and encryption halves
+fn decode_key(key: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let key = DataEncoding::decode64_url_bytes(key.trim());
+    if key.len() != KEY_LEN {
+        return Err("error: fernet key must be 32 url-safe base64-encoded bytes".into());
+    }
+    Ok((key[..16].to_vec(), key[16..].to_vec()))
+}
+
+#[wasm_bindgen]
+/// `Fernet` AES-128-CBC + HMAC-SHA256 tokens, compatible with Python `cryptography.fernet`
+pub struct Fernet {}
+
+#[wasm_bindgen]
+impl Fernet {
+    /// `try_encrypt_at` build a token with a given timestamp and IV
+    pub(crate) fn try_encrypt_at(
+        key: &str,
+        plaintext: &[u8],
+        timestamp: u64,
+        iv: &[u8],
+    ) -> Result<String, String> {
+        let (signing_key, encryption_key) = decode_key(key)?;
+        let ciphertext = cbc::Encryptor::<Aes128>::new_from_slices(&encryption_key, iv)
+            .map_err(|_| "error: fernet iv must be 16 bytes")?
+            .encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+
+        let mut token = Vec::with_capacity(HEADER_LEN + ciphertext.len() + HMAC_LEN);
+        token.push(FERNET_VERSION);
+        token.extend_from_slice(&timestamp.to_be_bytes());
+        token.extend_from_slice(iv);
+        token.extend_from_slice(&ciphertext);
+        let tag = Hash::compute_hmac::<Hmac<Sha256>>(&signing_key, &token);
+        token.extend_from_slice(&tag);
+        Ok(DataEncoding::encode64_url_bytes(&token))
+    }
+
+    /// `verify` check the version and HMAC, then decrypt
+    fn verify(key: &str, token: &str) -> Result<FernetToken, String> {
+        let (signing_key, encryption_key) = decode_key(key)?;
+        let data = DataEncoding::decode64_url_bytes(token.trim());
+        if data.len() < HEADER_LEN + BLOCK_LEN + HMAC_LEN
+            || !(data.len() - HEADER_LEN - HMAC_LEN).is_multiple_of(BLOCK_LEN)
+            || data[0] != FERNET_VERSION
+        {
+            return Err("error: invalid fernet token".into());
+        }
+        let (signed, tag) = data.split_at(data.len() - HMAC_LEN);
+        let expected = Hash::compute_hmac::<Hmac<Sha256>>(&signing_key, signed);
+        if !bool::from(expected.ct_eq(tag)) {
+            return Err("error: invalid fernet token".into());
+        }
+
+        let timestamp = u64::from_be_bytes(data[1..9].try_into().expect("8 bytes"));
+        let iv = &data[9..HEADER_LEN];
+        let plaintext = cbc::Decryptor::<Aes128>::new_from_slices(&encryption_key, iv)
+            .map_err(|_| "error: invalid fernet token")?
+            .decrypt_padded_vec_mut::<Pkcs7>(&signed[HEADER_LEN..])
+            .map_err(|_| "error: invalid fernet token")?;
+        Ok(FernetToken {
+            timestamp,
+            plaintext,
+        })
+    }
+
+    /// `try_decrypt` verify a token, optionally rejecting tokens older than `ttl` seconds at `now`
+    pub(crate) fn try_decrypt(
+        key: &str,
+        token: &str,
+        ttl: Option<u64>,
+        now: u64,
+    ) -> Result<Vec<u8>, String> {
+        let token = Self::verify(key, token)?;
+        if let Some(ttl) = ttl {
+            if token.timestamp.saturating_add(ttl) < now {
+                return Err("error: fernet token has expired".into());
+            }
+            if now.saturating_add(MAX_CLOCK_SKEW) < token.timestamp {
+                return Err("error: fernet token is from the future".into());
+            }
+        }
+        Ok(token.plaintext)
+    }
+
+    /// `generate_key` random Fernet key, url-safe base64 as `Fernet.generate_key()` in Python
+    pub fn generate_key() -> String {
+        DataEncoding::encode64_url_bytes(&SafeRandom::gen_bytes(KEY_LEN).raw())
+    }
+
+    /// `encrypt` create a Fernet token
+    ///
+    /// ### Arguments
+    /// * `key` - Url-safe base64 32-byte key
+    /// * `plaintext` - Data to encrypt
+    /// * `timestamp` - Creation time in unix seconds
+    pub fn encrypt(key: &str, plaintext: &[u8], timestamp: u64) -> Result<String, JsValue> {
+        let iv = SafeRandom::gen_bytes(IV_LEN).raw();
+        Self::try_encrypt_at(key, plaintext, timestamp, &iv).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `decrypt` verify and decrypt a token without an age limit
+    pub fn decrypt(key: &str, token: &str) -> Result<Vec<u8>, JsValue> {
+        Self::try_decrypt(key, token, None, 0).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `decrypt_with_ttl` verify and decrypt a token that must be at most `ttl` seconds old
+    ///
+    /// ### Arguments
+    /// * `key` - Url-safe base64 32-byte key
+    /// * `token` - Fernet token
+    /// * `ttl` - Maximum token age in seconds
+    /// * `now` - Current time in unix seconds
+    pub fn decrypt_with_ttl(
+        key: &str,
+        token: &str,
+        ttl: u64,
+        now: u64,
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::try_decrypt(key, token, Some(ttl), now).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `extract_timestamp` creation time of a token, after verifying it
+    pub fn extract_timestamp(key: &str, token: &str) -> Result<u64, JsValue> {
+        Self::verify(key, token)
+            .map(|token| token.timestamp)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[test]
+fn fernet_spec_test() {
+    // generate.json / verify.json from the Fernet spec
+    let key = "cw_0x689RpI-jtRR7oE8h_eQsKImvJapLeSbXpwF4e4=";
+    let token = "gAAAAAAdwJ6wAAECAwQFBgcICQoLDA0ODy021cpGVWKZ_eEwCGM4BLLF_5CV9dOPmrhuVUPgJobwOz7JcbmrR64jVmpU4IwqDA==";
+    let iv: Vec<u8> = (0..16).collect();
+    let now = 499_162_800;
+    assert_eq!(
+        token,
+        Fernet::try_encrypt_at(key, b"hello", now, &iv).unwrap()
+    );
+    assert_eq!(
+        b"hello".to_vec(),
+        Fernet::try_decrypt(key, token, Some(60), now).unwrap()
+    );
+    assert_eq!(now, Fernet::verify(key, token).unwrap().timestamp);
+
+    // invalid.json: expired, too far in the future, wrong key, tampered
+    assert!(Fernet::try_decrypt(key, token, Some(60), now + 61).is_err());
+    assert!(Fernet::try_decrypt(key, token, None, now + 1_000_000).is_ok());
+    assert!(Fernet::try_decrypt(key, token, Some(60), now - 61).is_err());
+    assert!(Fernet::try_decrypt(key, token, Some(60), now - 60).is_ok());
+    let other_key = "dw_0x689RpI-jtRR7oE8h_eQsKImvJapLeSbXpwF4e4=";
+    assert!(Fernet::try_decrypt(other_key, token, None, now).is_err());
+    let tampered = token.replacen("gAAAAAAd", "gAAAAAAe", 1);
+    assert!(Fernet::try_decrypt(key, &tampered, None, now).is_err());
+    assert!(Fernet::try_decrypt(key, &token[..40], None, now).is_err());
+    assert!(Fernet::try_decrypt("short", token, None, now).is_err());
+}
+
+#[test]
+fn fernet_roundtrip_test() {
+    let key = Fernet::generate_key();
+    println!("fernet key: {}", key);
+    assert_eq!(44, key.len());
+    for plaintext in [b"".to_vec(), vec![7u8; 16], b"session=abc".to_vec()] {
+        let token = Fernet::encrypt(&key, &plaintext, 1_700_000_000).unwrap();
+        assert!(token.starts_with("gAAAAA"));
+        assert_eq!(
+            plaintext,
+            Fernet::try_decrypt(&key, &token, Some(300), 1_700_000_100).unwrap()
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::tools::keyhandle::KeyHandle;
 use blake2::{Blake2b512, Blake2bMac512, Blake2s256};
 use blake3;
 use data_encoding::BASE64;
@@ -11,6 +12,7 @@ use rand::thread_rng;
 use sha2::Sha256;
 use sha3::Sha3_256;
 use sm3::Sm3;
+use subtle::ConstantTimeEq;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -31,6 +33,16 @@ impl Hash {
         hex_string
     }
 
+    /// `compute_hmac` keyed MAC over raw bytes
+    pub(crate) fn compute_hmac<M: Mac + hmac::digest::KeyInit>(
+        key: &[u8],
+        message: &[u8],
+    ) -> Vec<u8> {
+        let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC can take key of any size");
+        mac.update(message);
+        mac.finalize().into_bytes().to_vec()
+    }
+
     /// `gen_md5` encode an MD5 hash
     pub fn gen_md5(message: &str) -> String {
         Self::generate_hash::<Md5>(message)
@@ -122,11 +134,7 @@ impl Hash {
 
     /// `gen_hmac_sha256` encode a HMAC-SHA256 hash
     pub fn gen_hmac_sha256(key: &[u8], message: &str) -> String {
-        type HmacSha256 = Hmac<Sha256>;
-        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
-        mac.update(message.as_bytes());
-        let result = mac.finalize();
-        let code_bytes = result.into_bytes();
+        let code_bytes = Self::compute_hmac::<Hmac<Sha256>>(key, message.as_bytes());
         let hex_string = code_bytes
             .iter()
             .map(|byte| format!("{:02x}", byte))
@@ -205,6 +213,65 @@ impl Hash {
                 .is_ok(),
         )
     }
+
+    /// `hmac_sha256_with_handle` raw HMAC-SHA256 tag keyed by a `KeyHandle`
+    pub fn hmac_sha256_with_handle(key: &KeyHandle, message: &[u8]) -> Vec<u8> {
+        Self::compute_hmac::<Hmac<Sha256>>(key.bytes(), message)
+    }
+
+    /// `verify_hmac_sha256_with_handle` constant-time check of a raw HMAC-SHA256 tag
+    pub fn verify_hmac_sha256_with_handle(key: &KeyHandle, message: &[u8], tag: &[u8]) -> bool {
+        Self::hmac_sha256_with_handle(key, message)
+            .ct_eq(tag)
+            .into()
+    }
+
+    /// `ed25519_signing_key` Ed25519 key from a 32-byte seed
+    pub(crate) fn ed25519_signing_key(seed: &[u8]) -> Result<SigningKey, String> {
+        let seed: [u8; 32] = seed
+            .try_into()
+            .map_err(|_| "error: Ed25519 keys must be 32 bytes")?;
+        Ok(SigningKey::from(seed))
+    }
+
+    /// `ed25519_verify_bytes` check a raw 64-byte signature against a raw 32-byte public key
+    pub(crate) fn ed25519_verify_bytes(
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> bool {
+        let (Ok(vk_bytes), Ok(signature_bytes)) = (
+            <[u8; 32]>::try_from(public_key),
+            <[u8; 64]>::try_from(signature),
+        ) else {
+            return false;
+        };
+        let signature = ed25519_zebra::Signature::from_bytes(&signature_bytes);
+        VerificationKey::try_from(vk_bytes)
+            .and_then(|vk| vk.verify(&signature, message))
+            .is_ok()
+    }
+
+    /// `try_ed25519_sign_with_handle` raw 64-byte signature
+    pub(crate) fn try_ed25519_sign_with_handle(
+        key: &KeyHandle,
+        message: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let sk = Self::ed25519_signing_key(key.bytes())?;
+        Ok(sk.sign(message).to_bytes().to_vec())
+    }
+
+    /// `ed25519_sign_with_handle` sign with a 32-byte Ed25519 private key held in a `KeyHandle`
+    pub fn ed25519_sign_with_handle(key: &KeyHandle, message: &[u8]) -> Result<Vec<u8>, JsValue> {
+        Self::try_ed25519_sign_with_handle(key, message).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `ed25519_public_key_with_handle` 32-byte public key of an Ed25519 `KeyHandle`
+    pub fn ed25519_public_key_with_handle(key: &KeyHandle) -> Result<Vec<u8>, JsValue> {
+        Self::ed25519_signing_key(key.bytes())
+            .map(|sk| <[u8; 32]>::from(VerificationKey::from(&sk)).to_vec())
+            .map_err(|e| JsValue::from_str(&e))
+    }
 }
 
 #[test]
@@ -371,3 +438,47 @@ fn ed25519_sign_test() {
     let verify_b64 = verify_b64_option.unwrap();
     assert!(verify_b64);
 }
+
+#[test]
+fn key_handle_hash_test() {
+    let key = b"my secret and secure key";
+    let handle = KeyHandle::try_import(key, false).unwrap();
+    let tag = Hash::hmac_sha256_with_handle(&handle, b"input message");
+    assert_eq!(
+        Hash::gen_hmac_sha256(key, "input message"),
+        data_encoding::HEXLOWER.encode(&tag)
+    );
+    assert!(Hash::verify_hmac_sha256_with_handle(
+        &handle,
+        b"input message",
+        &tag
+    ));
+    assert!(!Hash::verify_hmac_sha256_with_handle(
+        &handle,
+        b"other message",
+        &tag
+    ));
+    assert!(!Hash::verify_hmac_sha256_with_handle(
+        &handle,
+        b"input message",
+        &tag[..16]
+    ));
+
+    let keypair = Hash::gen_ed25519_keypair();
+    let handle = KeyHandle::try_import(&keypair[32..], false).unwrap();
+    assert_eq!(
+        keypair[..32].to_vec(),
+        Hash::ed25519_public_key_with_handle(&handle).unwrap()
+    );
+    let signature = Hash::try_ed25519_sign_with_handle(&handle, b"hello").unwrap();
+    assert_eq!(
+        Some(true),
+        Hash::verify_ed25519_sign(
+            &BASE64.encode(&keypair[..32]),
+            "hello",
+            &BASE64.encode(&signature)
+        )
+    );
+    let long = KeyHandle::try_import(&[1u8; 64], false).unwrap();
+    assert!(Hash::try_ed25519_sign_with_handle(&long, b"hello").is_err());
+}
@@ -0,0 +1,783 @@
+use crate::tools::random::SafeRandom;
+use crate::tools::secret::AeadAlgorithm;
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use sha2::Sha256;
+use wasm_bindgen::prelude::*;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const KEM_ID: u16 = 0x0020;
+const KDF_ID: u16 = 0x0001;
+const AEAD_ID: u16 = 0x0003;
+const HPKE_AEAD: AeadAlgorithm = AeadAlgorithm::ChaCha20Poly1305;
+const MODE_BASE: u8 = 0x00;
+const MODE_AUTH: u8 = 0x02;
+/// Nsecret, Nenc, Npk, Nsk and Nk are all 32 for DHKEM(X25519) with ChaCha20-Poly1305
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SEALED_BOX_OVERHEAD: usize = 32 + 16;
+
+/// `kem_suite_id` "KEM" || kem_id
+fn kem_suite_id() -> Vec<u8> {
+    let mut suite_id = b"KEM".to_vec();
+    suite_id.extend_from_slice(&KEM_ID.to_be_bytes());
+    suite_id
+}
+
+/// `hpke_suite_id` "HPKE" || kem_id || kdf_id || aead_id
+fn hpke_suite_id() -> Vec<u8> {
+    let mut suite_id = b"HPKE".to_vec();
+    suite_id.extend_from_slice(&KEM_ID.to_be_bytes());
+    suite_id.extend_from_slice(&KDF_ID.to_be_bytes());
+    suite_id.extend_from_slice(&AEAD_ID.to_be_bytes());
+    suite_id
+}
+
+fn labeled_extract(suite_id: &[u8], salt: &[u8], label: &[u8], ikm: &[u8]) -> Vec<u8> {
+    let labeled_ikm = [b"HPKE-v1".as_slice(), suite_id, label, ikm].concat();
+    let (prk, _) = Hkdf::<Sha256>::extract(Some(salt), &labeled_ikm);
+    prk.to_vec()
+}
+
+fn labeled_expand(
+    suite_id: &[u8],
+    prk: &[u8],
+    label: &[u8],
+    info: &[u8],
+    len: usize,
+) -> Result<Vec<u8>, String> {
+    let length = u16::try_from(len).map_err(|_| "error: requested length is too long")?;
+    let labeled_info = [
+        length.to_be_bytes().as_slice(),
+        b"HPKE-v1",
+        suite_id,
+        label,
+        info,
+    ]
+    .concat();
+    let hk = Hkdf::<Sha256>::from_prk(prk).map_err(|_| "error: invalid pseudorandom key")?;
+    let mut okm = vec![0u8; len];
+    hk.expand(&labeled_info, &mut okm)
+        .map_err(|_| "error: requested length is too long")?;
+    Ok(okm)
+}
+
+fn parse_private_key(key: &[u8]) -> Result<StaticSecret, String> {
+    let bytes: [u8; KEY_LEN] = key
+        .try_into()
+        .map_err(|_| format!("error: private key must be {} bytes", KEY_LEN))?;
+    Ok(StaticSecret::from(bytes))
+}
+
+fn parse_public_key(key: &[u8]) -> Result<PublicKey, String> {
+    let bytes: [u8; KEY_LEN] = key
+        .try_into()
+        .map_err(|_| format!("error: public key must be {} bytes", KEY_LEN))?;
+    Ok(PublicKey::from(bytes))
+}
+
+/// `dh` X25519, rejecting low-order points that give an all-zero secret
+fn dh(secret: &StaticSecret, public: &PublicKey) -> Result<Vec<u8>, String> {
+    let shared = secret.diffie_hellman(public);
+    if !shared.was_contributory() {
+        return Err("error: invalid public key".into());
+    }
+    Ok(shared.as_bytes().to_vec())
+}
+
+/// `extract_and_expand` KEM shared secret from the DH output(s) and the KEM context
+fn extract_and_expand(dh: &[u8], kem_context: &[u8]) -> Result<Vec<u8>, String> {
+    let suite_id = kem_suite_id();
+    let eae_prk = labeled_extract(&suite_id, b"", b"eae_prk", dh);
+    labeled_expand(&suite_id, &eae_prk, b"shared_secret", kem_context, KEY_LEN)
+}
+
+/// `encap` (Auth)Encap with a given ephemeral key; returns the shared secret and `enc`
+fn encap(
+    pk_r: &PublicKey,
+    sk_s: Option<&StaticSecret>,
+    sk_e: &StaticSecret,
+) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let enc = PublicKey::from(sk_e).as_bytes().to_vec();
+    let mut dh_out = dh(sk_e, pk_r)?;
+    let mut kem_context = [enc.as_slice(), pk_r.as_bytes()].concat();
+    if let Some(sk_s) = sk_s {
+        dh_out.extend(dh(sk_s, pk_r)?);
+        kem_context.extend_from_slice(PublicKey::from(sk_s).as_bytes());
+    }
+    Ok((extract_and_expand(&dh_out, &kem_context)?, enc))
+}
+
+/// `decap` (Auth)Decap
+fn decap(enc: &[u8], sk_r: &StaticSecret, pk_s: Option<&PublicKey>) -> Result<Vec<u8>, String> {
+    let pk_e = parse_public_key(enc).map_err(|_| "error: invalid encapsulated key")?;
+    let mut dh_out = dh(sk_r, &pk_e)?;
+    let mut kem_context = [enc, PublicKey::from(sk_r).as_bytes()].concat();
+    if let Some(pk_s) = pk_s {
+        dh_out.extend(dh(sk_r, pk_s)?);
+        kem_context.extend_from_slice(pk_s.as_bytes());
+    }
+    extract_and_expand(&dh_out, &kem_context)
+}
+
+fn random_secret() -> StaticSecret {
+    let bytes: [u8; KEY_LEN] = SafeRandom::gen_bytes(KEY_LEN)
+        .raw()
+        .try_into()
+        .expect("32 random bytes");
+    StaticSecret::from(bytes)
+}
+
+#[wasm_bindgen]
+/// `X25519KeyPair` raw 32-byte X25519 keys for HPKE and sealed boxes
+pub struct X25519KeyPair {
+    private_key: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl X25519KeyPair {
+    fn from_secret(secret: &StaticSecret) -> X25519KeyPair {
+        X25519KeyPair {
+            private_key: secret.to_bytes().to_vec(),
+            public_key: PublicKey::from(secret).as_bytes().to_vec(),
+        }
+    }
+
+    /// `private_key` 32-byte private key, kept by the recipient
+    pub fn private_key(&self) -> Vec<u8> {
+        self.private_key.clone()
+    }
+
+    /// `public_key` 32-byte public key to share with senders
+    pub fn public_key(&self) -> Vec<u8> {
+        self.public_key.clone()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// Which side of an HPKE exchange a context belongs to
+pub enum HpkeRole {
+    /// holds `enc` and seals messages
+    Sender = 0,
+    /// rebuilt from `enc` and opens messages
+    Receiver = 1,
+}
+
+#[wasm_bindgen]
+/// `HpkeContext` HPKE encryption context for a sequence of messages
+pub struct HpkeContext {
+    role: HpkeRole,
+    enc: Vec<u8>,
+    key: Vec<u8>,
+    base_nonce: Vec<u8>,
+    exporter_secret: Vec<u8>,
+    seq: u64,
+}
+
+#[wasm_bindgen]
+impl HpkeContext {
+    /// `key_schedule` derive the AEAD key, base nonce and exporter secret (no PSK)
+    fn key_schedule(
+        role: HpkeRole,
+        mode: u8,
+        shared_secret: &[u8],
+        enc: Vec<u8>,
+        info: &[u8],
+    ) -> Result<HpkeContext, String> {
+        let suite_id = hpke_suite_id();
+        let psk_id_hash = labeled_extract(&suite_id, b"", b"psk_id_hash", b"");
+        let info_hash = labeled_extract(&suite_id, b"", b"info_hash", info);
+        let context = [&[mode], psk_id_hash.as_slice(), &info_hash].concat();
+        let secret = labeled_extract(&suite_id, shared_secret, b"secret", b"");
+        Ok(HpkeContext {
+            role,
+            enc,
+            key: labeled_expand(&suite_id, &secret, b"key", &context, KEY_LEN)?,
+            base_nonce: labeled_expand(&suite_id, &secret, b"base_nonce", &context, NONCE_LEN)?,
+            exporter_secret: labeled_expand(&suite_id, &secret, b"exp", &context, KEY_LEN)?,
+            seq: 0,
+        })
+    }
+
+    /// `next_nonce` base nonce XOR the sequence number, then advance the sequence
+    fn next_nonce(&mut self) -> Result<Vec<u8>, String> {
+        let mut nonce = self.base_nonce.clone();
+        for (n, s) in nonce[NONCE_LEN - 8..]
+            .iter_mut()
+            .zip(self.seq.to_be_bytes())
+        {
+            *n ^= s;
+        }
+        self.seq = self
+            .seq
+            .checked_add(1)
+            .ok_or("error: message limit reached")?;
+        Ok(nonce)
+    }
+
+    /// `try_seal` encrypt the next message
+    pub(crate) fn try_seal(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        if self.role != HpkeRole::Sender {
+            return Err("error: only a sender context can seal".into());
+        }
+        let nonce = self.next_nonce()?;
+        HPKE_AEAD.seal(&self.key, &nonce, plaintext, aad)
+    }
+
+    /// `try_open` decrypt the next message; the sequence only advances on success
+    pub(crate) fn try_open(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        if self.role != HpkeRole::Receiver {
+            return Err("error: only a receiver context can open".into());
+        }
+        let seq = self.seq;
+        let nonce = self.next_nonce()?;
+        HPKE_AEAD
+            .open(&self.key, &nonce, ciphertext, aad)
+            .inspect_err(|_| {
+                self.seq = seq;
+            })
+    }
+
+    /// `try_export` derive a secret bound to this context
+    pub(crate) fn try_export(
+        &self,
+        exporter_context: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, String> {
+        labeled_expand(
+            &hpke_suite_id(),
+            &self.exporter_secret,
+            b"sec",
+            exporter_context,
+            len,
+        )
+    }
+
+    /// `role` sender or receiver
+    pub fn role(&self) -> HpkeRole {
+        self.role
+    }
+
+    /// `enc` encapsulated key to send to the receiver (empty on the receiver side)
+    pub fn enc(&self) -> Vec<u8> {
+        self.enc.clone()
+    }
+
+    /// `sequence` number of messages sealed or opened so far
+    pub fn sequence(&self) -> u64 {
+        self.seq
+    }
+
+    /// `seal` encrypt the next message (sender only); messages must be opened in the same order
+    pub fn seal(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.try_seal(aad, plaintext)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `open` decrypt the next message (receiver only)
+    pub fn open(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.try_open(aad, ciphertext)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `export` derive `len` bytes of secret from the context and `exporter_context`
+    pub fn export(&self, exporter_context: &[u8], len: usize) -> Result<Vec<u8>, JsValue> {
+        self.try_export(exporter_context, len)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[wasm_bindgen]
+/// `Hpke` RFC 9180 with DHKEM(X25519, HKDF-SHA256), HKDF-SHA256 and ChaCha20-Poly1305
+pub struct Hpke {}
+
+#[wasm_bindgen]
+impl Hpke {
+    /// `sender_with_ephemeral` SetupBaseS / SetupAuthS with a given ephemeral key
+    fn sender_with_ephemeral(
+        recipient_public: &[u8],
+        sender_private: Option<&[u8]>,
+        info: &[u8],
+        sk_e: &StaticSecret,
+    ) -> Result<HpkeContext, String> {
+        let pk_r = parse_public_key(recipient_public)?;
+        let sk_s = sender_private.map(parse_private_key).transpose()?;
+        let (shared_secret, enc) = encap(&pk_r, sk_s.as_ref(), sk_e)?;
+        let mode = if sk_s.is_some() { MODE_AUTH } else { MODE_BASE };
+        HpkeContext::key_schedule(HpkeRole::Sender, mode, &shared_secret, enc, info)
+    }
+
+    /// `try_setup_sender` SetupBaseS, or SetupAuthS when a sender key is given
+    pub(crate) fn try_setup_sender(
+        recipient_public: &[u8],
+        sender_private: Option<&[u8]>,
+        info: &[u8],
+    ) -> Result<HpkeContext, String> {
+        Self::sender_with_ephemeral(recipient_public, sender_private, info, &random_secret())
+    }
+
+    /// `try_setup_receiver` SetupBaseR, or SetupAuthR when a sender public key is given
+    pub(crate) fn try_setup_receiver(
+        recipient_private: &[u8],
+        enc: &[u8],
+        sender_public: Option<&[u8]>,
+        info: &[u8],
+    ) -> Result<HpkeContext, String> {
+        let sk_r = parse_private_key(recipient_private)?;
+        let pk_s = sender_public.map(parse_public_key).transpose()?;
+        let shared_secret = decap(enc, &sk_r, pk_s.as_ref())?;
+        let mode = if pk_s.is_some() { MODE_AUTH } else { MODE_BASE };
+        HpkeContext::key_schedule(HpkeRole::Receiver, mode, &shared_secret, Vec::new(), info)
+    }
+
+    /// `try_seal` single-shot encryption; returns `enc || ciphertext`
+    pub(crate) fn try_seal(
+        recipient_public: &[u8],
+        sender_private: Option<&[u8]>,
+        info: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let mut context = Self::try_setup_sender(recipient_public, sender_private, info)?;
+        let mut out = context.enc();
+        out.extend(context.try_seal(aad, plaintext)?);
+        Ok(out)
+    }
+
+    /// `try_open` single-shot decryption of `enc || ciphertext`
+    pub(crate) fn try_open(
+        recipient_private: &[u8],
+        sender_public: Option<&[u8]>,
+        info: &[u8],
+        aad: &[u8],
+        sealed: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        if sealed.len() < KEY_LEN {
+            return Err("error: ciphertext is too short".into());
+        }
+        let (enc, ciphertext) = sealed.split_at(KEY_LEN);
+        let mut context = Self::try_setup_receiver(recipient_private, enc, sender_public, info)?;
+        context.try_open(aad, ciphertext)
+    }
+
+    /// `generate_keypair` random X25519 key pair
+    pub fn generate_keypair() -> X25519KeyPair {
+        X25519KeyPair::from_secret(&random_secret())
+    }
+
+    /// `derive_keypair` deterministic key pair from at least 32 bytes of seed (DeriveKeyPair)
+    pub fn derive_keypair(ikm: &[u8]) -> Result<X25519KeyPair, JsValue> {
+        Self::try_derive_keypair(ikm)
+            .map(|secret| X25519KeyPair::from_secret(&secret))
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `try_derive_keypair` DeriveKeyPair for DHKEM(X25519)
+    pub(crate) fn try_derive_keypair(ikm: &[u8]) -> Result<StaticSecret, String> {
+        if ikm.len() < KEY_LEN {
+            return Err(format!("error: seed must be at least {} bytes", KEY_LEN));
+        }
+        let suite_id = kem_suite_id();
+        let dkp_prk = labeled_extract(&suite_id, b"", b"dkp_prk", ikm);
+        let sk = labeled_expand(&suite_id, &dkp_prk, b"sk", b"", KEY_LEN)?;
+        parse_private_key(&sk)
+    }
+
+    /// `setup_sender` start a base-mode context to the recipient; send `enc()` along with the messages
+    pub fn setup_sender(recipient_public: &[u8], info: &[u8]) -> Result<HpkeContext, JsValue> {
+        Self::try_setup_sender(recipient_public, None, info).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `setup_receiver` recreate the base-mode context from `enc`
+    pub fn setup_receiver(
+        recipient_private: &[u8],
+        enc: &[u8],
+        info: &[u8],
+    ) -> Result<HpkeContext, JsValue> {
+        Self::try_setup_receiver(recipient_private, enc, None, info)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `setup_auth_sender` auth-mode context that also proves possession of the sender key
+    pub fn setup_auth_sender(
+        recipient_public: &[u8],
+        sender_private: &[u8],
+        info: &[u8],
+    ) -> Result<HpkeContext, JsValue> {
+        Self::try_setup_sender(recipient_public, Some(sender_private), info)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `setup_auth_receiver` auth-mode context; fails to open unless the sender key matches
+    pub fn setup_auth_receiver(
+        recipient_private: &[u8],
+        enc: &[u8],
+        sender_public: &[u8],
+        info: &[u8],
+    ) -> Result<HpkeContext, JsValue> {
+        Self::try_setup_receiver(recipient_private, enc, Some(sender_public), info)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `seal` encrypt one message to a public key
+    ///
+    /// ### Arguments
+    /// * `recipient_public` - 32-byte X25519 public key
+    /// * `info` - Application context bound into the key schedule
+    /// * `aad` - Associated data
+    /// * `plaintext` - Message
+    ///
+    /// ### Returns
+    /// 32-byte `enc` followed by the ciphertext and tag
+    pub fn seal(
+        recipient_public: &[u8],
+        info: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::try_seal(recipient_public, None, info, aad, plaintext)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `open` decrypt a message from `seal`
+    pub fn open(
+        recipient_private: &[u8],
+        info: &[u8],
+        aad: &[u8],
+        sealed: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::try_open(recipient_private, None, info, aad, sealed)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `seal_auth` encrypt one message in auth mode, authenticated by the sender's private key
+    pub fn seal_auth(
+        recipient_public: &[u8],
+        sender_private: &[u8],
+        info: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::try_seal(recipient_public, Some(sender_private), info, aad, plaintext)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `open_auth` decrypt a message from `seal_auth`, checking it came from `sender_public`
+    pub fn open_auth(
+        recipient_private: &[u8],
+        sender_public: &[u8],
+        info: &[u8],
+        aad: &[u8],
+        sealed: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::try_open(recipient_private, Some(sender_public), info, aad, sealed)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[wasm_bindgen]
+/// `SealedBox` anonymous public-key encryption compatible with libsodium `crypto_box_seal`
+pub struct SealedBox {}
+
+#[wasm_bindgen]
+impl SealedBox {
+    /// `try_seal` ephemeral public key || XSalsa20-Poly1305 box
+    pub(crate) fn try_seal(recipient_public: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let public = crypto_box::PublicKey::from_slice(recipient_public)
+            .map_err(|_| format!("error: public key must be {} bytes", KEY_LEN))?;
+        public
+            .seal(&mut OsRng, plaintext)
+            .map_err(|_| "error: encryption failed".into())
+    }
+
+    /// `try_open` open a sealed box with the recipient's private key
+    pub(crate) fn try_open(recipient_private: &[u8], sealed: &[u8]) -> Result<Vec<u8>, String> {
+        let secret = crypto_box::SecretKey::from_slice(recipient_private)
+            .map_err(|_| format!("error: private key must be {} bytes", KEY_LEN))?;
+        if sealed.len() < SEALED_BOX_OVERHEAD {
+            return Err("error: sealed box is too short".into());
+        }
+        secret
+            .unseal(sealed)
+            .map_err(|_| "error: decryption failed".into())
+    }
+
+    /// `generate_keypair` random X25519 key pair (`crypto_box_keypair`)
+    pub fn generate_keypair() -> X25519KeyPair {
+        Hpke::generate_keypair()
+    }
+
+    /// `seal` encrypt to a public key without identifying the sender (`crypto_box_seal`)
+    pub fn seal(recipient_public: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, JsValue> {
+        Self::try_seal(recipient_public, plaintext).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `open` decrypt a sealed box (`crypto_box_seal_open`)
+    pub fn open(recipient_private: &[u8], sealed: &[u8]) -> Result<Vec<u8>, JsValue> {
+        Self::try_open(recipient_private, sealed).map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[cfg(test)]
+fn from_hex(hex: &str) -> Vec<u8> {
+    data_encoding::HEXLOWER.decode(hex.as_bytes()).unwrap()
+}
+
+#[test]
+fn hpke_rfc9180_base_test() {
+    // RFC 9180 A.2.1: DHKEM(X25519, HKDF-SHA256), HKDF-SHA256, ChaCha20Poly1305, base mode
+    let info = from_hex("4f6465206f6e2061204772656369616e2055726e");
+    let sk_e = Hpke::try_derive_keypair(&from_hex(
+        "909a9b35d3dc4713a5e72a4da274b55d3d3821a37e5d099e74a647db583a904b",
+    ))
+    .unwrap();
+    let sk_r = from_hex("8057991eef8f1f1af18f4a9491d16a1ce333f695d4db8e38da75975c4478e0fb");
+    let pk_r = from_hex("4310ee97d88cc1f088a5576c77ab0cf5c3ac797f3d95139c6c84b5429c59662a");
+    assert_eq!(
+        pk_r,
+        X25519KeyPair::from_secret(&parse_private_key(&sk_r).unwrap()).public_key()
+    );
+
+    let mut sender = Hpke::sender_with_ephemeral(&pk_r, None, &info, &sk_e).unwrap();
+    let enc = sender.enc();
+    assert_eq!(
+        "1afa08d3dec047a643885163f1180476fa7ddb54c6a8029ea33f95796bf2ac4a",
+        data_encoding::HEXLOWER.encode(&enc)
+    );
+    assert_eq!(
+        from_hex("ad2744de8e17f4ebba575b3f5f5a8fa1f69c2a07f6e7500bc60ca6e3e3ec1c91"),
+        sender.key
+    );
+    assert_eq!(from_hex("5c4d98150661b848853b547f"), sender.base_nonce);
+
+    let plaintext = b"Beauty is truth, truth beauty";
+    let ct0 = sender.try_seal(b"Count-0", plaintext).unwrap();
+    let ct1 = sender.try_seal(b"Count-1", plaintext).unwrap();
+    assert_eq!(
+        from_hex(
+            "1c5250d8034ec2b784ba2cfd69dbdb8af406cfe3ff938e131f0def8c8b60b4db21993c62ce81883d2dd1b51a28"
+        ),
+        ct0
+    );
+    assert_eq!(
+        from_hex(
+            "6b53c051e4199c518de79594e1c4ab18b96f081549d45ce015be002090bb119e85285337cc95ba5f59992dc98c"
+        ),
+        ct1
+    );
+
+    let mut receiver = Hpke::try_setup_receiver(&sk_r, &enc, None, &info).unwrap();
+    assert!(receiver.try_open(b"Count-0", &ct1).is_err());
+    assert_eq!(0, receiver.sequence());
+    assert_eq!(
+        plaintext.to_vec(),
+        receiver.try_open(b"Count-0", &ct0).unwrap()
+    );
+    assert_eq!(
+        plaintext.to_vec(),
+        receiver.try_open(b"Count-1", &ct1).unwrap()
+    );
+
+    for (context, exported) in [
+        (
+            "",
+            "4bbd6243b8bb54cec311fac9df81841b6fd61f56538a775e7c80a9f40160606e",
+        ),
+        (
+            "00",
+            "8c1df14732580e5501b00f82b10a1647b40713191b7c1240ac80e2b68808ba69",
+        ),
+        (
+            "54657374436f6e74657874",
+            "5acb09211139c43b3090489a9da433e8a30ee7188ba8b0a9a1ccf0c229283e53",
+        ),
+    ] {
+        let context = from_hex(context);
+        assert_eq!(from_hex(exported), sender.try_export(&context, 32).unwrap());
+        assert_eq!(
+            from_hex(exported),
+            receiver.try_export(&context, 32).unwrap()
+        );
+    }
+    assert!(sender.try_open(b"Count-0", &ct0).is_err());
+    assert!(receiver.try_seal(b"", b"x").is_err());
+}
+
+#[test]
+fn hpke_auth_test() {
+    // RFC 9180 A.2.3: DHKEM(X25519, HKDF-SHA256), HKDF-SHA256, ChaCha20Poly1305, auth mode
+    let info = from_hex("4f6465206f6e2061204772656369616e2055726e");
+    let sk_e = Hpke::try_derive_keypair(&from_hex(
+        "938d3daa5a8904540bc24f48ae90eed3f4f7f11839560597b55e7c9598c996c0",
+    ))
+    .unwrap();
+    let sk_r = Hpke::try_derive_keypair(&from_hex(
+        "64835d5ee64aa7aad57c6f2e4f758f7696617f8829e70bc9ac7a5ef95d1c756c",
+    ))
+    .unwrap()
+    .to_bytes()
+    .to_vec();
+    let pk_r = from_hex("1a478716d63cb2e16786ee93004486dc151e988b34b475043d3e0175bdb01c44");
+    let sk_s = from_hex("77d114e0212be51cb1d76fa99dd41cfd4d0166b08caa09074430a6c59ef17879");
+    let pk_s = from_hex("13640af826b722fc04feaa4de2f28fbd5ecc03623b317834e7ff4120dbe73062");
+    assert_eq!(
+        pk_r,
+        X25519KeyPair::from_secret(&parse_private_key(&sk_r).unwrap()).public_key()
+    );
+    assert_eq!(
+        pk_s,
+        X25519KeyPair::from_secret(&parse_private_key(&sk_s).unwrap()).public_key()
+    );
+
+    let mut sender = Hpke::sender_with_ephemeral(&pk_r, Some(&sk_s), &info, &sk_e).unwrap();
+    let enc = sender.enc();
+    assert_eq!(
+        "f7674cc8cd7baa5872d1f33dbaffe3314239f6197ddf5ded1746760bfc847e0e",
+        data_encoding::HEXLOWER.encode(&enc)
+    );
+    assert_eq!(
+        from_hex("5915b106ae17eeb8c93723991631d63f7040eb5bdf31812efd43fdf09b9409a3"),
+        sender.key
+    );
+    assert_eq!(from_hex("3bee1268cdf5a085e288f299"), sender.base_nonce);
+
+    let plaintext = b"Beauty is truth, truth beauty";
+    let ct0 = sender.try_seal(b"Count-0", plaintext).unwrap();
+    let ct1 = sender.try_seal(b"Count-1", plaintext).unwrap();
+    assert_eq!(
+        from_hex(
+            "877a9d089a3a26dcb792e0a4bc95c5df3aa94c860fe3e669603e51b29d683dbd4f6d675defa2102a16f0278982"
+        ),
+        ct0
+    );
+    assert_eq!(
+        from_hex(
+            "b75b729c2fafcc9d39fc4e3d10d742ac9291935ab51ebf6da7b217de33549288b2f75147f96781f380ce9575ee"
+        ),
+        ct1
+    );
+
+    let mut receiver = Hpke::try_setup_receiver(&sk_r, &enc, Some(&pk_s), &info).unwrap();
+    assert_eq!(
+        plaintext.to_vec(),
+        receiver.try_open(b"Count-0", &ct0).unwrap()
+    );
+    assert_eq!(
+        plaintext.to_vec(),
+        receiver.try_open(b"Count-1", &ct1).unwrap()
+    );
+
+    for (context, exported) in [
+        (
+            "",
+            "2173df5093044f6a799eb8c15c3847ed9520576889263cc7a15a1255b4189627",
+        ),
+        (
+            "00",
+            "dc937943b71090a8b6f8aa1badc2e445e0e2d54172cdf288a23f192f6412cbe5",
+        ),
+        (
+            "54657374436f6e74657874",
+            "38f91752a508b10db9a8af915c3c55229d08fdf759ecc41ba90efcf8d644e8e9",
+        ),
+    ] {
+        let context = from_hex(context);
+        assert_eq!(from_hex(exported), sender.try_export(&context, 32).unwrap());
+        assert_eq!(
+            from_hex(exported),
+            receiver.try_export(&context, 32).unwrap()
+        );
+    }
+
+    // the wrong sender key, or base mode, derives a different context
+    let other = Hpke::generate_keypair().public_key();
+    let mut forged = Hpke::try_setup_receiver(&sk_r, &enc, Some(&other), &info).unwrap();
+    assert!(forged.try_open(b"Count-0", &ct0).is_err());
+    let mut base = Hpke::try_setup_receiver(&sk_r, &enc, None, &info).unwrap();
+    assert!(base.try_open(b"Count-0", &ct0).is_err());
+}
+
+#[test]
+fn hpke_single_shot_test() {
+    let recipient = Hpke::generate_keypair();
+    let sender = Hpke::generate_keypair();
+    let sealed = Hpke::seal(&recipient.public_key(), b"app", b"header", b"hello").unwrap();
+    assert_eq!(32 + 5 + 16, sealed.len());
+    assert_eq!(
+        b"hello".to_vec(),
+        Hpke::open(&recipient.private_key(), b"app", b"header", &sealed).unwrap()
+    );
+    assert!(Hpke::try_open(&recipient.private_key(), None, b"other", b"header", &sealed).is_err());
+    assert!(Hpke::try_open(&recipient.private_key(), None, b"app", b"", &sealed).is_err());
+    assert!(Hpke::try_open(&sender.private_key(), None, b"app", b"header", &sealed).is_err());
+
+    let sealed = Hpke::seal_auth(
+        &recipient.public_key(),
+        &sender.private_key(),
+        b"app",
+        b"",
+        b"from sender",
+    )
+    .unwrap();
+    assert_eq!(
+        b"from sender".to_vec(),
+        Hpke::open_auth(
+            &recipient.private_key(),
+            &sender.public_key(),
+            b"app",
+            b"",
+            &sealed
+        )
+        .unwrap()
+    );
+
+    // base mode from another implementation (pyca/cryptography), private key 01..20
+    let private_key: Vec<u8> = (1..=32).collect();
+    let sealed = from_hex(
+        "1133679bc45fc22afd6ac7ff2b809a46b40393b078007fb5e61934619cd15178b3e630732c79e31f672285258c6afe9e543552c37fb88e51633efc1c2a8d",
+    );
+    assert_eq!(
+        b"hpke by python".to_vec(),
+        Hpke::open(&private_key, b"info", b"", &sealed).unwrap()
+    );
+
+    // low-order points and malformed keys are rejected
+    assert!(Hpke::try_seal(&[0u8; 32], None, b"", b"", b"x").is_err());
+    assert!(Hpke::try_seal(&[1u8; 16], None, b"", b"", b"x").is_err());
+    assert!(Hpke::try_open(&recipient.private_key(), None, b"", b"", &[0u8; 60]).is_err());
+    assert!(Hpke::try_derive_keypair(&[1u8; 16]).is_err());
+}
+
+#[test]
+fn sealed_box_test() {
+    // sealed with libsodium crypto_box_seal to the key pair with private key 01..20
+    let private_key: Vec<u8> = (1..=32).collect();
+    let keypair = X25519KeyPair::from_secret(&parse_private_key(&private_key).unwrap());
+    assert_eq!(
+        from_hex("07a37cbc142093c8b755dc1b10e86cb426374ad16aa853ed0bdfc0b2b86d1c7c"),
+        keypair.public_key()
+    );
+    let sealed = from_hex(
+        "4bfab7a307f5c513f34cbc5d2006ec21d604b2cf40b1472d19f824fbcfa0ee45fbbd744bc132475cf6a7d4342af84e6bb6b3edb2b5f5dcc55c5de2fb085a000e6f4cbe",
+    );
+    assert_eq!(
+        b"sealed by libsodium".to_vec(),
+        SealedBox::try_open(&private_key, &sealed).unwrap()
+    );
+
+    let recipient = SealedBox::generate_keypair();
+    let sealed = SealedBox::seal(&recipient.public_key(), b"anonymous").unwrap();
+    assert_eq!(32 + 9 + 16, sealed.len());
+    assert_eq!(
+        b"anonymous".to_vec(),
+        SealedBox::open(&recipient.private_key(), &sealed).unwrap()
+    );
+    let mut tampered = sealed.clone();
+    tampered[40] ^= 1;
+    assert!(SealedBox::try_open(&recipient.private_key(), &tampered).is_err());
+    assert!(SealedBox::try_open(&private_key, &sealed).is_err());
+    assert!(SealedBox::try_open(&private_key, &sealed[..40]).is_err());
+    assert!(SealedBox::try_seal(&[1u8; 31], b"x").is_err());
+}
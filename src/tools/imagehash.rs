@@ -0,0 +1,306 @@
+use std::f64::consts::PI;
+
+use wasm_bindgen::prelude::*;
+
+const HASH_SIZE: usize = 8;
+const PHASH_SIZE: usize = HASH_SIZE * 4;
+
+#[wasm_bindgen]
+/// `ImageHash` perceptual image hashing
+pub struct ImageHash {}
+
+#[wasm_bindgen]
+impl ImageHash {
+    /// `prepare` validate the greyscale buffer and convert it to floats
+    fn prepare(gray_bytes: &[u8], width: u32, height: u32) -> Result<Vec<f64>, JsValue> {
+        if width == 0 || height == 0 {
+            return Err(JsValue::from_str("width and height must be greater than 0"));
+        }
+        if (width as usize).checked_mul(height as usize) != Some(gray_bytes.len()) {
+            return Err(JsValue::from_str(
+                "gray_bytes length does not match width * height",
+            ));
+        }
+        Ok(gray_bytes.iter().map(|&v| v as f64).collect())
+    }
+
+    /// `resize` area-average resample of a greyscale image
+    fn resize(pixels: &[f64], width: usize, height: usize, tw: usize, th: usize) -> Vec<f64> {
+        let sx = width as f64 / tw as f64;
+        let sy = height as f64 / th as f64;
+        let mut out = vec![0f64; tw * th];
+
+        for ty in 0..th {
+            let y0 = ty as f64 * sy;
+            let y1 = y0 + sy;
+            for tx in 0..tw {
+                let x0 = tx as f64 * sx;
+                let x1 = x0 + sx;
+
+                let mut sum = 0f64;
+                let mut area = 0f64;
+                let mut y = y0.floor() as usize;
+                while (y as f64) < y1 && y < height {
+                    let wy = (y1.min(y as f64 + 1.0) - y0.max(y as f64)).max(0.0);
+                    let mut x = x0.floor() as usize;
+                    while (x as f64) < x1 && x < width {
+                        let wx = (x1.min(x as f64 + 1.0) - x0.max(x as f64)).max(0.0);
+                        sum += pixels[y * width + x] * wx * wy;
+                        area += wx * wy;
+                        x += 1;
+                    }
+                    y += 1;
+                }
+                out[ty * tw + tx] = if area > 0.0 { sum / area } else { 0.0 };
+            }
+        }
+        out
+    }
+
+    /// `median` median of the given values
+    fn median(values: &[f64]) -> f64 {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let mid = sorted.len() / 2;
+        if sorted.len().is_multiple_of(2) {
+            (sorted[mid - 1] + sorted[mid]) / 2.0
+        } else {
+            sorted[mid]
+        }
+    }
+
+    /// `to_hex` pack bits (row-major, MSB first) into a hex string
+    fn to_hex(bits: impl Iterator<Item = bool>) -> String {
+        let hash = bits.fold(0u64, |acc, bit| (acc << 1) | bit as u64);
+        format!("{:016x}", hash)
+    }
+
+    /// `dct_1d` unnormalized DCT-II of a single row
+    fn dct_1d(input: &[f64]) -> Vec<f64> {
+        let n = input.len();
+        (0..n)
+            .map(|k| {
+                input
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &x)| x * (PI * k as f64 * (2 * i + 1) as f64 / (2 * n) as f64).cos())
+                    .sum::<f64>()
+                    * 2.0
+            })
+            .collect()
+    }
+
+    /// `ahash` average hash: 8x8 thumbnail compared to its mean
+    ///
+    /// ### Arguments
+    /// * `gray_bytes` - Grayscale pixel data (0-255)
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    ///
+    /// ### Returns
+    /// 64-bit hash as a 16-character hex string
+    pub fn ahash(gray_bytes: &[u8], width: u32, height: u32) -> Result<String, JsValue> {
+        let pixels = Self::prepare(gray_bytes, width, height)?;
+        let small = Self::resize(
+            &pixels,
+            width as usize,
+            height as usize,
+            HASH_SIZE,
+            HASH_SIZE,
+        );
+        let mean = small.iter().sum::<f64>() / small.len() as f64;
+        Ok(Self::to_hex(small.iter().map(|&v| v > mean)))
+    }
+
+    /// `dhash` difference hash: horizontal gradient of a 9x8 thumbnail
+    ///
+    /// ### Arguments
+    /// * `gray_bytes` - Grayscale pixel data (0-255)
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    ///
+    /// ### Returns
+    /// 64-bit hash as a 16-character hex string
+    pub fn dhash(gray_bytes: &[u8], width: u32, height: u32) -> Result<String, JsValue> {
+        let pixels = Self::prepare(gray_bytes, width, height)?;
+        let small = Self::resize(
+            &pixels,
+            width as usize,
+            height as usize,
+            HASH_SIZE + 1,
+            HASH_SIZE,
+        );
+        let bits = (0..HASH_SIZE).flat_map(|y| {
+            let row = &small[y * (HASH_SIZE + 1)..(y + 1) * (HASH_SIZE + 1)];
+            (0..HASH_SIZE).map(move |x| row[x + 1] > row[x])
+        });
+        Ok(Self::to_hex(bits))
+    }
+
+    /// `phash` perceptual hash: low frequencies of a 32x32 DCT compared to their median
+    ///
+    /// ### Arguments
+    /// * `gray_bytes` - Grayscale pixel data (0-255)
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    ///
+    /// ### Returns
+    /// 64-bit hash as a 16-character hex string
+    pub fn phash(gray_bytes: &[u8], width: u32, height: u32) -> Result<String, JsValue> {
+        let pixels = Self::prepare(gray_bytes, width, height)?;
+        let small = Self::resize(
+            &pixels,
+            width as usize,
+            height as usize,
+            PHASH_SIZE,
+            PHASH_SIZE,
+        );
+
+        // separable 2D DCT: rows first, then columns
+        let rows: Vec<Vec<f64>> = small.chunks(PHASH_SIZE).map(Self::dct_1d).collect();
+        let mut dct = vec![0f64; PHASH_SIZE * PHASH_SIZE];
+        for x in 0..PHASH_SIZE {
+            let column: Vec<f64> = rows.iter().map(|row| row[x]).collect();
+            for (y, v) in Self::dct_1d(&column).into_iter().enumerate() {
+                dct[y * PHASH_SIZE + x] = v;
+            }
+        }
+
+        let low: Vec<f64> = (0..HASH_SIZE)
+            .flat_map(|y| dct[y * PHASH_SIZE..y * PHASH_SIZE + HASH_SIZE].to_vec())
+            .collect();
+        let median = Self::median(&low);
+        Ok(Self::to_hex(low.iter().map(|&v| v > median)))
+    }
+
+    /// `whash` wavelet hash: Haar LL band at 8x8 compared to its median
+    ///
+    /// ### Arguments
+    /// * `gray_bytes` - Grayscale pixel data (0-255)
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    ///
+    /// ### Returns
+    /// 64-bit hash as a 16-character hex string
+    pub fn whash(gray_bytes: &[u8], width: u32, height: u32) -> Result<String, JsValue> {
+        let pixels = Self::prepare(gray_bytes, width, height)?;
+
+        // largest power of two not above the shorter side, at least the hash size
+        let shorter = width.min(height) as usize;
+        let mut scale = HASH_SIZE;
+        while scale * 2 <= shorter {
+            scale *= 2;
+        }
+
+        let mut ll = Self::resize(&pixels, width as usize, height as usize, scale, scale);
+        while scale > HASH_SIZE {
+            let half = scale / 2;
+            let mut next = vec![0f64; half * half];
+            for y in 0..half {
+                for x in 0..half {
+                    let i = 2 * y * scale + 2 * x;
+                    next[y * half + x] =
+                        (ll[i] + ll[i + 1] + ll[i + scale] + ll[i + scale + 1]) / 2.0;
+                }
+            }
+            ll = next;
+            scale = half;
+        }
+
+        let median = Self::median(&ll);
+        Ok(Self::to_hex(ll.iter().map(|&v| v > median)))
+    }
+
+    /// `hamming_distance` count differing bits between two hex hashes
+    pub fn hamming_distance(hash_a: &str, hash_b: &str) -> Result<u32, JsValue> {
+        if hash_a.len() != hash_b.len() {
+            return Err(JsValue::from_str("hash lengths do not match"));
+        }
+        let mut distance = 0u32;
+        for (a, b) in hash_a.chars().zip(hash_b.chars()) {
+            let a = a
+                .to_digit(16)
+                .ok_or_else(|| JsValue::from_str(&format!("invalid hex character: {}", a)))?;
+            let b = b
+                .to_digit(16)
+                .ok_or_else(|| JsValue::from_str(&format!("invalid hex character: {}", b)))?;
+            distance += (a ^ b).count_ones();
+        }
+        Ok(distance)
+    }
+}
+
+#[cfg(test)]
+fn gradient_image(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity((width * height) as usize);
+    for _y in 0..height {
+        for x in 0..width {
+            bytes.push((x * 255 / (width - 1)) as u8);
+        }
+    }
+    bytes
+}
+
+#[test]
+fn imagehash_gradient_test() {
+    let image = gradient_image(64, 48);
+
+    let ahash = ImageHash::ahash(&image, 64, 48).unwrap();
+    println!("ahash: {}", ahash);
+    assert_eq!("0f0f0f0f0f0f0f0f", ahash);
+
+    let dhash = ImageHash::dhash(&image, 64, 48).unwrap();
+    println!("dhash: {}", dhash);
+    assert_eq!("ffffffffffffffff", dhash);
+
+    let phash = ImageHash::phash(&image, 64, 48).unwrap();
+    println!("phash: {}", phash);
+    assert_eq!(16, phash.len());
+
+    let whash = ImageHash::whash(&image, 64, 48).unwrap();
+    println!("whash: {}", whash);
+    assert_eq!("0f0f0f0f0f0f0f0f", whash);
+}
+
+#[cfg(test)]
+fn wave_image(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let v = 128.0 + 60.0 * (x as f64 / 7.0).sin() + 60.0 * (y as f64 / 5.0).cos();
+            bytes.push(v as u8);
+        }
+    }
+    bytes
+}
+
+#[test]
+fn imagehash_distance_test() {
+    let image = wave_image(64, 64);
+    let brighter: Vec<u8> = image.iter().map(|&v| v.saturating_add(5)).collect();
+    let inverted: Vec<u8> = image.iter().map(|&v| 255 - v).collect();
+
+    type HashFn = fn(&[u8], u32, u32) -> Result<String, JsValue>;
+    let hashers: [(&str, HashFn); 4] = [
+        ("ahash", ImageHash::ahash),
+        ("dhash", ImageHash::dhash),
+        ("phash", ImageHash::phash),
+        ("whash", ImageHash::whash),
+    ];
+    for (name, hasher) in hashers {
+        let original = hasher(&image, 64, 64).unwrap();
+        let similar = hasher(&brighter, 64, 64).unwrap();
+        let different = hasher(&inverted, 64, 64).unwrap();
+
+        let near = ImageHash::hamming_distance(&original, &similar).unwrap();
+        let far = ImageHash::hamming_distance(&original, &different).unwrap();
+        println!("{}: near={} far={}", name, near, far);
+        assert!(near <= 4);
+        assert!(far > 20);
+    }
+
+    assert_eq!(
+        64,
+        ImageHash::hamming_distance("0000000000000000", "ffffffffffffffff").unwrap()
+    );
+}
@@ -0,0 +1,282 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use wasm_bindgen::prelude::*;
+
+// parameters are read back from untrusted ciphertexts, so keep them within what a wasm32
+// instance (4 GiB address space) can actually run: 1 GiB for Argon2id, 128 MiB x 16 for scrypt
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 10;
+const MAX_P_COST: u32 = 16;
+const MAX_LOG_N: u8 = 20;
+const MAX_R: u32 = 16;
+// decrypting callers refuse recorded settings above this budget unless they pass their own limit
+pub(crate) const DEFAULT_MAX_MEMORY_KIB: u64 = 256 * 1024;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// Memory-hard password KDF
+pub enum KdfAlgorithm {
+    /// Argon2id (RFC 9106)
+    Argon2id = 0,
+    /// scrypt (RFC 7914)
+    Scrypt = 1,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// `KdfParams` password KDF parameters, recorded next to the data they protect
+pub struct KdfParams {
+    algorithm: KdfAlgorithm,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    log_n: u8,
+    r: u32,
+}
+
+#[wasm_bindgen]
+impl KdfParams {
+    /// `try_argon2id` validate Argon2id parameters
+    pub(crate) fn try_argon2id(m_cost: u32, t_cost: u32, p_cost: u32) -> Result<KdfParams, String> {
+        if !(1..=MAX_P_COST).contains(&p_cost) {
+            return Err(format!("error: p must be between 1 and {}", MAX_P_COST));
+        }
+        if !(8 * p_cost..=MAX_M_COST).contains(&m_cost) {
+            return Err(format!(
+                "error: m must be between {} and {} KiB",
+                8 * p_cost,
+                MAX_M_COST
+            ));
+        }
+        if !(1..=MAX_T_COST).contains(&t_cost) {
+            return Err(format!("error: t must be between 1 and {}", MAX_T_COST));
+        }
+        Ok(KdfParams {
+            algorithm: KdfAlgorithm::Argon2id,
+            m_cost,
+            t_cost,
+            p_cost,
+            log_n: 0,
+            r: 0,
+        })
+    }
+
+    /// `try_scrypt` validate scrypt parameters
+    pub(crate) fn try_scrypt(log_n: u8, r: u32, p: u32) -> Result<KdfParams, String> {
+        if !(1..=MAX_LOG_N).contains(&log_n) {
+            return Err(format!("error: ln must be between 1 and {}", MAX_LOG_N));
+        }
+        if !(1..=MAX_R).contains(&r) {
+            return Err(format!("error: r must be between 1 and {}", MAX_R));
+        }
+        if !(1..=MAX_P_COST).contains(&p) {
+            return Err(format!("error: p must be between 1 and {}", MAX_P_COST));
+        }
+        Ok(KdfParams {
+            algorithm: KdfAlgorithm::Scrypt,
+            m_cost: 0,
+            t_cost: 0,
+            p_cost: p,
+            log_n,
+            r,
+        })
+    }
+
+    /// `argon2id` Argon2id with memory `m_cost` (KiB), `t_cost` passes and `p_cost` lanes
+    pub fn argon2id(m_cost: u32, t_cost: u32, p_cost: u32) -> Result<KdfParams, JsValue> {
+        Self::try_argon2id(m_cost, t_cost, p_cost).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `scrypt` scrypt with cost `N = 2^log_n`, block size `r` and parallelism `p`
+    pub fn scrypt(log_n: u8, r: u32, p: u32) -> Result<KdfParams, JsValue> {
+        Self::try_scrypt(log_n, r, p).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `recommended` Argon2id m=19456 KiB, t=2, p=1 (OWASP baseline)
+    pub fn recommended() -> KdfParams {
+        Self::try_argon2id(19 * 1024, 2, 1).expect("recommended parameters are valid")
+    }
+
+    /// `algorithm` KDF algorithm
+    pub fn algorithm(&self) -> KdfAlgorithm {
+        self.algorithm
+    }
+
+    /// `m_cost` Argon2id memory in KiB (0 for scrypt)
+    pub fn m_cost(&self) -> u32 {
+        self.m_cost
+    }
+
+    /// `t_cost` Argon2id passes (0 for scrypt)
+    pub fn t_cost(&self) -> u32 {
+        self.t_cost
+    }
+
+    /// `p_cost` parallelism for both algorithms
+    pub fn p_cost(&self) -> u32 {
+        self.p_cost
+    }
+
+    /// `log_n` scrypt cost exponent (0 for Argon2id)
+    pub fn log_n(&self) -> u8 {
+        self.log_n
+    }
+
+    /// `r` scrypt block size (0 for Argon2id)
+    pub fn r(&self) -> u32 {
+        self.r
+    }
+
+    /// `memory_kib` memory needed to derive a key, in KiB (`m` for Argon2id, `128 * r * N` for scrypt)
+    pub fn memory_kib(&self) -> u64 {
+        match self.algorithm {
+            KdfAlgorithm::Argon2id => u64::from(self.m_cost),
+            KdfAlgorithm::Scrypt => ((128 * u64::from(self.r)) << self.log_n) / 1024,
+        }
+    }
+
+    /// `check_memory` reject parameters that need more than `max_kib` before deriving anything
+    pub(crate) fn check_memory(&self, max_kib: u64) -> Result<(), String> {
+        if self.memory_kib() > max_kib {
+            return Err(format!(
+                "error: kdf needs {} KiB of memory, above the limit of {} KiB",
+                self.memory_kib(),
+                max_kib
+            ));
+        }
+        Ok(())
+    }
+
+    /// `derive` stretch a secret into `len` key bytes
+    pub(crate) fn derive(&self, secret: &[u8], salt: &[u8], len: usize) -> Result<Vec<u8>, String> {
+        let mut out = vec![0u8; len];
+        match self.algorithm {
+            KdfAlgorithm::Argon2id => {
+                let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(len))
+                    .map_err(|e| format!("error: {}", e))?;
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(secret, salt, &mut out)
+                    .map_err(|e| format!("error: {}", e))?;
+            }
+            KdfAlgorithm::Scrypt => {
+                let params = scrypt::Params::new(self.log_n, self.r, self.p_cost, len)
+                    .map_err(|e| format!("error: {}", e))?;
+                scrypt::scrypt(secret, salt, &params, &mut out)
+                    .map_err(|e| format!("error: {}", e))?;
+            }
+        }
+        Ok(out)
+    }
+
+    /// `derive_key` derive `len` key bytes from a passphrase and salt (Argon2id needs at least 8 bytes of salt)
+    pub fn derive_key(
+        &self,
+        passphrase: &str,
+        salt: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, JsValue> {
+        self.derive(passphrase.as_bytes(), salt, len)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `encode` `argon2id$m=..,t=..,p=..` or `scrypt$ln=..,r=..,p=..`
+    pub(crate) fn encode(&self) -> String {
+        match self.algorithm {
+            KdfAlgorithm::Argon2id => format!(
+                "argon2id$m={},t={},p={}",
+                self.m_cost, self.t_cost, self.p_cost
+            ),
+            KdfAlgorithm::Scrypt => {
+                format!("scrypt$ln={},r={},p={}", self.log_n, self.r, self.p_cost)
+            }
+        }
+    }
+
+    /// `parse` read the two `$`-separated fields written by `encode`
+    pub(crate) fn parse(name: &str, fields: &str) -> Result<KdfParams, String> {
+        let mut values = Vec::new();
+        for field in fields.split(',') {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("error: invalid kdf parameter: {}", field))?;
+            let value: u32 = value
+                .parse()
+                .map_err(|_| format!("error: invalid kdf parameter: {}", field))?;
+            values.push((key, value));
+        }
+        let keys: Vec<&str> = values.iter().map(|(k, _)| *k).collect();
+        let numbers: Vec<u32> = values.iter().map(|(_, v)| *v).collect();
+        match (name, keys.as_slice()) {
+            ("argon2id", ["m", "t", "p"]) => Self::try_argon2id(numbers[0], numbers[1], numbers[2]),
+            ("scrypt", ["ln", "r", "p"]) => {
+                let log_n = u8::try_from(numbers[0]).map_err(|_| "error: ln is out of range")?;
+                Self::try_scrypt(log_n, numbers[1], numbers[2])
+            }
+            _ => Err(format!("error: unsupported kdf: {}${}", name, fields)),
+        }
+    }
+}
+
+#[test]
+fn kdf_scrypt_rfc7914_test() {
+    // RFC 7914 section 12, second vector
+    let params = KdfParams::scrypt(10, 8, 16).unwrap();
+    let key = params.derive(b"password", b"NaCl", 64).unwrap();
+    assert_eq!(
+        "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b3731622eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640",
+        data_encoding::HEXLOWER.encode(&key)
+    );
+}
+
+#[test]
+fn kdf_params_test() {
+    let argon = KdfParams::argon2id(256, 1, 1).unwrap();
+    let a = argon.derive_key("correct horse", b"saltsalt", 32).unwrap();
+    let b = argon.derive_key("correct horse", b"saltsalt", 32).unwrap();
+    let c = argon.derive_key("correct horse", b"pepperrr", 32).unwrap();
+    println!("argon2id: {}", data_encoding::HEXLOWER.encode(&a));
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    assert!(argon.derive(b"x", b"short", 32).is_err());
+
+    assert_eq!("argon2id$m=256,t=1,p=1", argon.encode());
+    assert_eq!(
+        argon,
+        KdfParams::parse("argon2id", "m=256,t=1,p=1").unwrap()
+    );
+    let scrypt = KdfParams::scrypt(4, 8, 1).unwrap();
+    assert_eq!("scrypt$ln=4,r=8,p=1", scrypt.encode());
+    assert_eq!(scrypt, KdfParams::parse("scrypt", "ln=4,r=8,p=1").unwrap());
+    assert_eq!(KdfAlgorithm::Argon2id, KdfParams::recommended().algorithm());
+    assert_eq!(19456, KdfParams::recommended().m_cost());
+
+    assert!(KdfParams::parse("argon2id", "t=1,m=256,p=1").is_err());
+    assert!(KdfParams::parse("argon2i", "m=256,t=1,p=1").is_err());
+    assert!(KdfParams::parse("scrypt", "ln=300,r=8,p=1").is_err());
+    assert!(KdfParams::try_argon2id(4, 1, 1).is_err());
+    assert!(KdfParams::try_argon2id(256, 0, 1).is_err());
+    assert!(KdfParams::try_argon2id(u32::MAX, 1, 1).is_err());
+    assert!(KdfParams::try_scrypt(40, 8, 1).is_err());
+    assert!(KdfParams::try_scrypt(10, 0, 1).is_err());
+
+    // caps keep parameters read from untrusted input runnable on wasm32
+    assert!(KdfParams::try_argon2id(1024 * 1024, 10, 1).is_ok());
+    assert!(KdfParams::try_argon2id(1024 * 1024 + 1, 1, 1).is_err());
+    assert!(KdfParams::try_argon2id(256, 11, 1).is_err());
+    assert!(KdfParams::try_scrypt(20, 16, 1).is_ok());
+    assert!(KdfParams::try_scrypt(21, 8, 1).is_err());
+    assert!(KdfParams::try_scrypt(10, 17, 1).is_err());
+    assert!(KdfParams::parse("scrypt", "ln=24,r=32,p=1").is_err());
+    assert!(KdfParams::parse("argon2id", "m=4194304,t=16,p=1").is_err());
+
+    assert_eq!(256, argon.memory_kib());
+    assert_eq!(
+        16 * 1024,
+        KdfParams::try_scrypt(14, 8, 1).unwrap().memory_kib()
+    );
+    assert_eq!(
+        2 * 1024 * 1024,
+        KdfParams::try_scrypt(20, 16, 1).unwrap().memory_kib()
+    );
+    assert!(argon.check_memory(256).is_ok());
+    assert!(argon.check_memory(255).is_err());
+}
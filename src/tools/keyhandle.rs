@@ -0,0 +1,131 @@
+use data_encoding::BASE64;
+use getrandom::getrandom;
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+const MIN_KEY_LEN: usize = 16;
+const MAX_KEY_LEN: usize = 64;
+
+#[wasm_bindgen]
+/// `KeyHandle` key material kept in wasm memory and wiped on `free()`
+///
+/// Pass the handle to the `*_with_handle` functions of `Secret` and `Hash` instead of
+/// base64 strings. The bytes can only be read back when created as extractable.
+pub struct KeyHandle {
+    key: Zeroizing<Vec<u8>>,
+    extractable: bool,
+}
+
+#[wasm_bindgen]
+impl KeyHandle {
+    /// `check_len` key lengths accepted by a handle
+    fn check_len(len: usize) -> Result<(), String> {
+        if !(MIN_KEY_LEN..=MAX_KEY_LEN).contains(&len) {
+            return Err(format!(
+                "error: key length must be between {} and {} bytes",
+                MIN_KEY_LEN, MAX_KEY_LEN
+            ));
+        }
+        Ok(())
+    }
+
+    /// `try_import` copy key bytes into a new handle
+    pub(crate) fn try_import(key: &[u8], extractable: bool) -> Result<KeyHandle, String> {
+        Self::check_len(key.len())?;
+        // allocated once at its final size so no stale copy is left behind by a reallocation
+        let mut bytes = Zeroizing::new(Vec::with_capacity(key.len()));
+        bytes.extend_from_slice(key);
+        Ok(KeyHandle {
+            key: bytes,
+            extractable,
+        })
+    }
+
+    /// `bytes` key material for use inside the crate
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// `try_generate` random key of `len` bytes
+    pub(crate) fn try_generate(len: usize, extractable: bool) -> Result<KeyHandle, String> {
+        Self::check_len(len)?;
+        let mut key = Zeroizing::new(vec![0u8; len]);
+        getrandom(&mut key)
+            .map_err(|e| format!("error: failed to generate random bytes: {}", e))?;
+        Ok(KeyHandle { key, extractable })
+    }
+
+    /// `generate` random key created inside wasm
+    ///
+    /// ### Arguments
+    /// * `len` - Key length in bytes (16 to 64)
+    /// * `extractable` - Whether `export` may return the bytes
+    pub fn generate(len: usize, extractable: bool) -> Result<KeyHandle, JsValue> {
+        Self::try_generate(len, extractable).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `import` copy raw key bytes into a handle; wipe the source `Uint8Array` afterwards
+    pub fn import(key: &[u8], extractable: bool) -> Result<KeyHandle, JsValue> {
+        Self::try_import(key, extractable).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `import_base64` import a base64 key, as taken by the string-based `Secret` functions
+    pub fn import_base64(key_b64: &str, extractable: bool) -> Result<KeyHandle, JsValue> {
+        let key = Zeroizing::new(
+            BASE64
+                .decode(key_b64.as_bytes())
+                .map_err(|_| JsValue::from_str("error: invalid base64 key"))?,
+        );
+        Self::import(&key, extractable)
+    }
+
+    /// `key_len` key length in bytes
+    pub fn key_len(&self) -> usize {
+        self.key.len()
+    }
+
+    /// `extractable` whether the key bytes can be exported
+    pub fn extractable(&self) -> bool {
+        self.extractable
+    }
+
+    /// `try_export` key bytes, only for extractable handles
+    pub(crate) fn try_export(&self) -> Result<Vec<u8>, String> {
+        if !self.extractable {
+            return Err("error: key is not extractable".into());
+        }
+        Ok(self.key.to_vec())
+    }
+
+    /// `export` raw key bytes; fails unless the handle was created as extractable
+    pub fn export(&self) -> Result<Vec<u8>, JsValue> {
+        self.try_export().map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `export_base64` base64 key; fails unless the handle was created as extractable
+    pub fn export_base64(&self) -> Result<String, JsValue> {
+        self.export().map(|key| BASE64.encode(&key))
+    }
+}
+
+#[test]
+fn key_handle_test() {
+    let handle = KeyHandle::generate(32, false).unwrap();
+    assert_eq!(32, handle.key_len());
+    assert!(!handle.extractable());
+    assert!(handle.try_export().is_err());
+    assert_ne!(vec![0u8; 32], handle.bytes());
+
+    let key: Vec<u8> = (0..32).collect();
+    let handle = KeyHandle::try_import(&key, true).unwrap();
+    assert_eq!(key, handle.try_export().unwrap());
+    assert_eq!(key.len(), handle.key.capacity());
+    let handle = KeyHandle::import_base64(&BASE64.encode(&key), false).unwrap();
+    assert_eq!(key, handle.bytes());
+
+    assert!(KeyHandle::try_generate(8, true).is_err());
+    assert!(KeyHandle::try_generate(65, true).is_err());
+    assert!(KeyHandle::try_generate(usize::MAX, true).is_err());
+    assert!(KeyHandle::try_import(&[1u8; 8], true).is_err());
+    assert!(KeyHandle::try_import(&[1u8; 65], true).is_err());
+}
@@ -1,8 +1,21 @@
+pub mod age;
+pub mod fernet;
 pub mod hash;
+pub mod hpke;
+pub mod imagehash;
+pub mod kdf;
+pub mod keyhandle;
+pub mod ocra;
 pub mod otp;
+pub mod otpuri;
 pub mod parse;
+pub mod paseto;
 pub mod password;
 pub mod pinyin;
 pub mod qrcode;
 pub mod random;
+pub mod recovery;
 pub mod secret;
+pub mod shamir;
+pub mod stream;
+pub mod vault;
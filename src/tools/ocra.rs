@@ -0,0 +1,517 @@
+use crate::tools::otp::{OtpAlgorithm, codes_match, dynamic_truncate, format_numeric};
+use data_encoding::{HEXLOWER, HEXUPPER};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use wasm_bindgen::prelude::*;
+
+const QUESTION_LEN: usize = 128;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum QuestionFormat {
+    Numeric,
+    Alphanumeric,
+    Hex,
+}
+
+/// parsed `OCRA-1:HOTP-<hash>-<digits>:<data input>` suite
+struct OcraSuite {
+    algorithm: OtpAlgorithm,
+    digits: u32,
+    counter: bool,
+    question: QuestionFormat,
+    question_len: usize,
+    password: Option<OtpAlgorithm>,
+    session_len: Option<usize>,
+    time_step: Option<u64>,
+}
+
+impl OcraSuite {
+    fn parse(suite: &str) -> Result<OcraSuite, String> {
+        let parts: Vec<&str> = suite.split(':').collect();
+        if parts.len() != 3 || parts[0] != "OCRA-1" {
+            return Err(format!("error: invalid ocra suite: {}", suite));
+        }
+
+        let crypto: Vec<&str> = parts[1].split('-').collect();
+        if crypto.len() != 3 || crypto[0] != "HOTP" {
+            return Err(format!("error: invalid crypto function: {}", parts[1]));
+        }
+        let algorithm = OtpAlgorithm::from_name(crypto[1])
+            .ok_or_else(|| format!("error: unsupported hash: {}", crypto[1]))?;
+        let digits: u32 = crypto[2]
+            .parse()
+            .ok()
+            .filter(|d| *d == 0 || (4..=10).contains(d))
+            .ok_or_else(|| format!("error: invalid digits: {}", crypto[2]))?;
+
+        let mut parsed = OcraSuite {
+            algorithm,
+            digits,
+            counter: false,
+            question: QuestionFormat::Numeric,
+            question_len: 0,
+            password: None,
+            session_len: None,
+            time_step: None,
+        };
+        let mut has_question = false;
+        for item in parts[2].split('-') {
+            let (kind, value) = item.split_at(item.len().min(1));
+            match kind {
+                "C" if value.is_empty() && !has_question && !parsed.counter => {
+                    parsed.counter = true
+                }
+                "Q" if !has_question => {
+                    let (format, len) = value.split_at(value.len().min(1));
+                    parsed.question = match format {
+                        "N" => QuestionFormat::Numeric,
+                        "A" => QuestionFormat::Alphanumeric,
+                        "H" => QuestionFormat::Hex,
+                        _ => return Err(format!("error: invalid question format: {}", item)),
+                    };
+                    parsed.question_len = len
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|l| len.len() == 2 && (4..=64).contains(l))
+                        .ok_or_else(|| format!("error: invalid question length: {}", item))?;
+                    has_question = true;
+                }
+                "P" if has_question && parsed.password.is_none() => {
+                    parsed.password = Some(
+                        OtpAlgorithm::from_name(value)
+                            .ok_or_else(|| format!("error: unsupported pin hash: {}", item))?,
+                    );
+                }
+                "S" if has_question && parsed.session_len.is_none() => {
+                    parsed.session_len = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|l| value.len() == 3 && [64, 128, 256, 512].contains(l))
+                            .ok_or_else(|| format!("error: invalid session length: {}", item))?,
+                    );
+                }
+                "T" if has_question && parsed.time_step.is_none() => {
+                    let (count, unit) = value.split_at(value.len().saturating_sub(1));
+                    let count: u64 = count
+                        .parse()
+                        .map_err(|_| format!("error: invalid time step: {}", item))?;
+                    let step = match unit {
+                        "S" if (1..=59).contains(&count) => count,
+                        "M" if (1..=59).contains(&count) => count * 60,
+                        "H" if (1..=48).contains(&count) => count * 3600,
+                        _ => return Err(format!("error: invalid time step: {}", item)),
+                    };
+                    parsed.time_step = Some(step);
+                }
+                _ => return Err(format!("error: invalid data input: {}", item)),
+            }
+        }
+        if !has_question {
+            return Err("error: ocra suite requires a question".into());
+        }
+        Ok(parsed)
+    }
+
+    /// question as 128 bytes: hex nibbles left-aligned and zero-padded
+    ///
+    /// each challenge may be at most the suite's question length; mutual
+    /// challenge-response concatenates the client and server challenges
+    fn encode_question(&self, challenges: &[String]) -> Result<Vec<u8>, String> {
+        if challenges.is_empty() || challenges.iter().any(|c| c.is_empty()) {
+            return Err("error: question is required".into());
+        }
+        if challenges
+            .iter()
+            .any(|c| c.chars().count() > self.question_len)
+        {
+            return Err(format!(
+                "error: question must be at most {} characters",
+                self.question_len
+            ));
+        }
+        let question = challenges.concat();
+        let question = question.as_str();
+        let hex = match self.question {
+            QuestionFormat::Numeric => {
+                if !question.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err("error: question must be numeric".into());
+                }
+                decimal_to_hex(question)
+            }
+            QuestionFormat::Alphanumeric => {
+                if !question.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                    return Err("error: question must be alphanumeric".into());
+                }
+                HEXUPPER.encode(question.as_bytes())
+            }
+            QuestionFormat::Hex => {
+                if !question.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err("error: question must be hex".into());
+                }
+                question.to_uppercase()
+            }
+        };
+        if hex.len() > QUESTION_LEN * 2 {
+            return Err("error: question is too long".into());
+        }
+        let padded = format!("{:0<width$}", hex, width = QUESTION_LEN * 2);
+        Ok(HEXUPPER
+            .decode(padded.as_bytes())
+            .expect("padded question is valid hex"))
+    }
+
+    fn hash_pin(algorithm: OtpAlgorithm, pin: &str) -> Vec<u8> {
+        match algorithm {
+            OtpAlgorithm::Sha1 => Sha1::digest(pin.as_bytes()).to_vec(),
+            OtpAlgorithm::Sha256 => Sha256::digest(pin.as_bytes()).to_vec(),
+            OtpAlgorithm::Sha512 => Sha512::digest(pin.as_bytes()).to_vec(),
+        }
+    }
+
+    /// `suite || 0x00 || C || Q || P || S || T`
+    fn message(&self, suite: &str, input: &OcraInput) -> Result<Vec<u8>, String> {
+        let mut msg = suite.as_bytes().to_vec();
+        msg.push(0);
+
+        if self.counter {
+            let counter = input
+                .counter
+                .ok_or("error: ocra suite requires a counter")?;
+            msg.extend_from_slice(&counter.to_be_bytes());
+        }
+
+        msg.extend(self.encode_question(&input.question)?);
+
+        if let Some(algorithm) = self.password {
+            let hash = match (&input.pin_hash, &input.pin) {
+                (Some(hash), _) => hash.clone(),
+                (None, Some(pin)) => Self::hash_pin(algorithm, pin),
+                (None, None) => return Err("error: ocra suite requires a pin".into()),
+            };
+            if hash.len() != algorithm.output_len() {
+                return Err("error: pin hash length does not match suite".into());
+            }
+            msg.extend(hash);
+        }
+
+        if let Some(len) = self.session_len {
+            let session = input
+                .session
+                .as_ref()
+                .ok_or("error: ocra suite requires session information")?;
+            if session.len() > len {
+                return Err(format!(
+                    "error: session information must be at most {} bytes",
+                    len
+                ));
+            }
+            let mut block = vec![0u8; len];
+            block[..session.len()].copy_from_slice(session);
+            msg.extend(block);
+        }
+
+        if let Some(step) = self.time_step {
+            let timestamp = input
+                .timestamp
+                .ok_or("error: ocra suite requires a timestamp")?;
+            msg.extend_from_slice(&(timestamp / step).to_be_bytes());
+        }
+        Ok(msg)
+    }
+}
+
+/// decimal string to uppercase hex without leading zeros
+fn decimal_to_hex(decimal: &str) -> String {
+    let mut bytes: Vec<u8> = vec![0];
+    for digit in decimal.bytes().map(|b| (b - b'0') as u32) {
+        let mut carry = digit;
+        for byte in bytes.iter_mut().rev() {
+            let v = *byte as u32 * 10 + carry;
+            *byte = (v & 0xff) as u8;
+            carry = v >> 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, (carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+    let hex = HEXUPPER.encode(&bytes);
+    let trimmed = hex.trim_start_matches('0');
+    if trimmed.is_empty() {
+        "0".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+/// `OcraInput` OCRA data input values (question, counter, PIN, session, timestamp)
+pub struct OcraInput {
+    question: Vec<String>,
+    counter: Option<u64>,
+    pin: Option<String>,
+    pin_hash: Option<Vec<u8>>,
+    session: Option<Vec<u8>>,
+    timestamp: Option<u64>,
+}
+
+#[wasm_bindgen]
+impl OcraInput {
+    /// `new` create input with the challenge question
+    pub fn new(question: &str) -> OcraInput {
+        OcraInput {
+            question: vec![question.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// `new_mutual` create input for mutual challenge-response, with the
+    /// challenge of the side computing the response first
+    pub fn new_mutual(first: &str, second: &str) -> OcraInput {
+        OcraInput {
+            question: vec![first.to_string(), second.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// `set_counter` counter value for `C` suites
+    pub fn set_counter(&mut self, counter: u64) {
+        self.counter = Some(counter);
+    }
+
+    /// `set_pin` PIN for `P` suites, hashed with the suite hash
+    pub fn set_pin(&mut self, pin: &str) {
+        self.pin = Some(pin.to_string());
+    }
+
+    /// `set_pin_hash` already hashed PIN for `P` suites
+    pub fn set_pin_hash(&mut self, pin_hash: Vec<u8>) {
+        self.pin_hash = Some(pin_hash);
+    }
+
+    /// `set_session` session information for `S` suites
+    pub fn set_session(&mut self, session: Vec<u8>) {
+        self.session = Some(session);
+    }
+
+    /// `set_timestamp` Unix time in seconds for `T` suites
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = Some(timestamp);
+    }
+}
+
+#[wasm_bindgen]
+/// `Ocra` OATH challenge-response (RFC 6287)
+pub struct Ocra {}
+
+#[wasm_bindgen]
+impl Ocra {
+    /// `compute` compute an OCRA response
+    fn compute(suite: &str, key: &[u8], input: &OcraInput) -> Result<String, String> {
+        let parsed = OcraSuite::parse(suite)?;
+        let msg = parsed.message(suite, input)?;
+        let digest = parsed.algorithm.mac(key, &msg);
+        if parsed.digits == 0 {
+            return Ok(HEXLOWER.encode(&digest));
+        }
+        Ok(format_numeric(dynamic_truncate(&digest), parsed.digits))
+    }
+
+    /// `is_valid_suite` check an OCRA suite string such as `OCRA-1:HOTP-SHA256-8:QN08-PSHA1`
+    pub fn is_valid_suite(suite: &str) -> bool {
+        OcraSuite::parse(suite).is_ok()
+    }
+
+    /// `generate` compute an OCRA response
+    ///
+    /// ### Arguments
+    /// * `suite` - OCRA suite, e.g. `OCRA-1:HOTP-SHA1-6:QN08`
+    /// * `key` - Shared secret bytes
+    /// * `input` - Question and the optional counter, PIN, session and timestamp
+    ///
+    /// ### Returns
+    /// Response code (hex HMAC when the suite has 0 digits)
+    pub fn generate(suite: &str, key: &[u8], input: &OcraInput) -> Result<String, JsValue> {
+        Self::compute(suite, key, input).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `verify` compare an OCRA response in constant time
+    pub fn verify(
+        suite: &str,
+        key: &[u8],
+        input: &OcraInput,
+        response: &str,
+    ) -> Result<bool, JsValue> {
+        let expected = Self::generate(suite, key, input)?;
+        Ok(codes_match(&expected, response))
+    }
+}
+
+#[cfg(test)]
+const KEY20: &[u8] = b"12345678901234567890";
+#[cfg(test)]
+const KEY32: &[u8] = b"12345678901234567890123456789012";
+#[cfg(test)]
+const KEY64: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+#[test]
+fn ocra_one_way_test() {
+    // RFC 6287 Appendix C.1
+    let suite = "OCRA-1:HOTP-SHA1-6:QN08";
+    let expected = [
+        "237653", "243178", "653583", "740991", "608993", "388898", "816933", "224598", "750600",
+        "294470",
+    ];
+    for (i, code) in expected.iter().enumerate() {
+        let input = OcraInput::new(&i.to_string().repeat(8));
+        let result = Ocra::generate(suite, KEY20, &input).unwrap();
+        println!("{} {}: {}", suite, i, result);
+        assert_eq!(*code, result);
+    }
+
+    let suite = "OCRA-1:HOTP-SHA256-8:C-QN08-PSHA1";
+    let expected = [
+        "65347737", "86775851", "78192410", "71565254", "10104329", "65983500", "70069104",
+        "91771096", "75011558", "08522129",
+    ];
+    for (counter, code) in expected.iter().enumerate() {
+        let mut input = OcraInput::new("12345678");
+        input.set_counter(counter as u64);
+        input.set_pin("1234");
+        let result = Ocra::generate(suite, KEY32, &input).unwrap();
+        assert_eq!(*code, result);
+    }
+
+    let suite = "OCRA-1:HOTP-SHA256-8:QN08-PSHA1";
+    let expected = ["83238735", "01501458", "17957585", "86776967", "86807031"];
+    for (i, code) in expected.iter().enumerate() {
+        let mut input = OcraInput::new(&i.to_string().repeat(8));
+        input.set_pin_hash(
+            HEXLOWER
+                .decode(b"7110eda4d09e062aa5e4a390b0a572ac0d2c0220")
+                .unwrap(),
+        );
+        let result = Ocra::generate(suite, KEY32, &input).unwrap();
+        assert_eq!(*code, result);
+    }
+
+    let suite = "OCRA-1:HOTP-SHA512-8:C-QN08";
+    let expected = [
+        "07016083", "63947962", "70123924", "25341727", "33203315", "34205738", "44343969",
+        "51946085", "20403879", "31409299",
+    ];
+    for (i, code) in expected.iter().enumerate() {
+        let mut input = OcraInput::new(&i.to_string().repeat(8));
+        input.set_counter(i as u64);
+        let result = Ocra::generate(suite, KEY64, &input).unwrap();
+        assert_eq!(*code, result);
+    }
+
+    let suite = "OCRA-1:HOTP-SHA512-8:QN08-T1M";
+    let expected = ["95209754", "55907591", "22048402", "24218844", "36209546"];
+    for (i, code) in expected.iter().enumerate() {
+        let mut input = OcraInput::new(&i.to_string().repeat(8));
+        input.set_timestamp(0x132d0b6 * 60);
+        let result = Ocra::generate(suite, KEY64, &input).unwrap();
+        assert_eq!(*code, result);
+    }
+}
+
+#[test]
+fn ocra_mutual_test() {
+    // RFC 6287 Appendix C.2
+    let server = "OCRA-1:HOTP-SHA256-8:QA08";
+    let client = "OCRA-1:HOTP-SHA256-8:QA08";
+    let server_codes = ["28247970", "01984843", "65387857", "03351211", "83412541"];
+    let client_codes = ["15510767", "90175646", "33777207", "95285278", "28934924"];
+    for i in 0..5 {
+        let client_challenge = format!("CLI2222{}", i);
+        let server_challenge = format!("SRV1111{}", i);
+        let server_input = OcraInput::new_mutual(&client_challenge, &server_challenge);
+        let client_input = OcraInput::new_mutual(&server_challenge, &client_challenge);
+        assert_eq!(
+            server_codes[i],
+            Ocra::generate(server, KEY32, &server_input).unwrap()
+        );
+        assert_eq!(
+            client_codes[i],
+            Ocra::generate(client, KEY32, &client_input).unwrap()
+        );
+    }
+
+    let server = "OCRA-1:HOTP-SHA512-8:QA08";
+    let client = "OCRA-1:HOTP-SHA512-8:QA08-PSHA1";
+    let server_codes = ["79496648", "76831980", "12250499", "90856481", "12761449"];
+    let client_codes = ["18806276", "70020315", "01600026", "18951020", "32528969"];
+    for i in 0..5 {
+        let client_challenge = format!("CLI2222{}", i);
+        let server_challenge = format!("SRV1111{}", i);
+        let server_input = OcraInput::new_mutual(&client_challenge, &server_challenge);
+        let mut client_input = OcraInput::new_mutual(&server_challenge, &client_challenge);
+        client_input.set_pin("1234");
+        assert_eq!(
+            server_codes[i],
+            Ocra::generate(server, KEY64, &server_input).unwrap()
+        );
+        assert_eq!(
+            client_codes[i],
+            Ocra::generate(client, KEY64, &client_input).unwrap()
+        );
+    }
+}
+
+#[test]
+fn ocra_signature_test() {
+    // RFC 6287 Appendix C.3
+    let suite = "OCRA-1:HOTP-SHA256-8:QA08";
+    let expected = ["53095496", "04110475", "31331128", "76028668", "46554205"];
+    for (i, code) in expected.iter().enumerate() {
+        let input = OcraInput::new(&format!("SIG1{}000", i));
+        assert_eq!(*code, Ocra::generate(suite, KEY32, &input).unwrap());
+    }
+
+    let suite = "OCRA-1:HOTP-SHA512-8:QA10-T1M";
+    let expected = ["77537423", "31970405", "10235557", "95213541", "65360607"];
+    for (i, code) in expected.iter().enumerate() {
+        let mut input = OcraInput::new(&format!("SIG1{}00000", i));
+        input.set_timestamp(0x132d0b6 * 60);
+        let result = Ocra::generate(suite, KEY64, &input).unwrap();
+        println!("{} {}: {}", suite, i, result);
+        assert_eq!(*code, result);
+        assert!(Ocra::verify(suite, KEY64, &input, code).unwrap());
+        assert!(!Ocra::verify(suite, KEY64, &input, "00000000").unwrap());
+    }
+}
+
+#[test]
+fn ocra_suite_test() {
+    assert!(Ocra::is_valid_suite("OCRA-1:HOTP-SHA1-6:QN08"));
+    assert!(Ocra::is_valid_suite("OCRA-1:HOTP-SHA256-8:C-QN08-PSHA1"));
+    assert!(Ocra::is_valid_suite("OCRA-1:HOTP-SHA512-8:QA10-S064-T1M"));
+    assert!(Ocra::is_valid_suite("OCRA-1:HOTP-SHA1-0:QH40"));
+    assert!(!Ocra::is_valid_suite("OCRA-2:HOTP-SHA1-6:QN08"));
+    assert!(!Ocra::is_valid_suite("OCRA-1:HOTP-MD5-6:QN08"));
+    assert!(!Ocra::is_valid_suite("OCRA-1:HOTP-SHA1-3:QN08"));
+    assert!(!Ocra::is_valid_suite("OCRA-1:HOTP-SHA1-6:C"));
+    assert!(!Ocra::is_valid_suite("OCRA-1:HOTP-SHA1-6:QN08-C"));
+    assert!(!Ocra::is_valid_suite("OCRA-1:HOTP-SHA1-6:QX08"));
+    assert!(!Ocra::is_valid_suite("OCRA-1:HOTP-SHA1-6:QN08-S100"));
+    assert!(!Ocra::is_valid_suite("OCRA-1:HOTP-SHA1-6:QN08-T60M"));
+
+    // missing inputs required by the suite
+    let input = OcraInput::new("12345678");
+    assert!(Ocra::compute("OCRA-1:HOTP-SHA1-6:C-QN08", KEY20, &input).is_err());
+    assert!(Ocra::compute("OCRA-1:HOTP-SHA1-6:QN08-PSHA1", KEY20, &input).is_err());
+    assert!(Ocra::compute("OCRA-1:HOTP-SHA1-6:QA08", KEY20, &OcraInput::new("ab-cd")).is_err());
+
+    // questions longer than the suite allows (RFC 6287 section 5.1)
+    let input = OcraInput::new("123456789");
+    assert!(Ocra::compute("OCRA-1:HOTP-SHA1-6:QN08", KEY20, &input).is_err());
+    assert!(Ocra::compute("OCRA-1:HOTP-SHA1-6:QN09", KEY20, &input).is_ok());
+    let input = OcraInput::new_mutual("CLI22220", "SRV111100");
+    assert!(Ocra::compute("OCRA-1:HOTP-SHA256-8:QA08", KEY32, &input).is_err());
+    let input = OcraInput::new_mutual("CLI22220", "");
+    assert!(Ocra::compute("OCRA-1:HOTP-SHA256-8:QA08", KEY32, &input).is_err());
+}
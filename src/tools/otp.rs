@@ -1,25 +1,694 @@
-use otpauth::TOTP;
+use crate::tools::hash::Hash;
+use crate::tools::otpuri::OtpAuthUri;
+use crate::tools::qrcode::QRCode;
+use crate::tools::random::SafeRandom;
+use data_encoding::{BASE32_NOPAD, Specification};
+use hmac::Hmac;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use subtle::ConstantTimeEq;
 use wasm_bindgen::prelude::*;
 
+const HOTP_DIGITS: u32 = 6;
+const MIN_DIGITS: u32 = 6;
+const MAX_DIGITS: u32 = 10;
+const MAX_CUSTOM_LENGTH: u32 = 16;
+// every counter in the window costs an HMAC, so a huge window would stall the caller
+const MAX_HOTP_WINDOW: u64 = 100;
+const MAX_TOTP_SKEW: u32 = 10;
+const STEAM_ALPHABET: &str = "23456789BCDFGHJKMNPQRTVWXY";
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// HMAC algorithm for OTP generation
+pub enum OtpAlgorithm {
+    /// HMAC-SHA1 (RFC 4226 default)
+    Sha1 = 0,
+    /// HMAC-SHA256
+    Sha256 = 1,
+    /// HMAC-SHA512
+    Sha512 = 2,
+}
+
+impl OtpAlgorithm {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            OtpAlgorithm::Sha1 => "SHA1",
+            OtpAlgorithm::Sha256 => "SHA256",
+            OtpAlgorithm::Sha512 => "SHA512",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<OtpAlgorithm> {
+        match name.to_uppercase().as_str() {
+            "SHA1" => Some(OtpAlgorithm::Sha1),
+            "SHA256" => Some(OtpAlgorithm::Sha256),
+            "SHA512" => Some(OtpAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    /// digest size in bytes, also the recommended secret length
+    pub(crate) fn output_len(&self) -> usize {
+        match self {
+            OtpAlgorithm::Sha1 => 20,
+            OtpAlgorithm::Sha256 => 32,
+            OtpAlgorithm::Sha512 => 64,
+        }
+    }
+
+    pub(crate) fn mac(&self, key: &[u8], message: &[u8]) -> Vec<u8> {
+        match self {
+            OtpAlgorithm::Sha1 => Hash::compute_hmac::<Hmac<Sha1>>(key, message),
+            OtpAlgorithm::Sha256 => Hash::compute_hmac::<Hmac<Sha256>>(key, message),
+            OtpAlgorithm::Sha512 => Hash::compute_hmac::<Hmac<Sha512>>(key, message),
+        }
+    }
+}
+
+/// Base32 secrets as authenticator apps export them: non-zero trailing bits are ignored and
+/// a final character that cannot complete a byte is dropped, as the `otpauth` crate did
+pub(crate) fn decode_secret(secret: &str) -> Result<Vec<u8>, String> {
+    let normalized: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .collect::<String>()
+        .to_uppercase();
+    let usable = match normalized.len() % 8 {
+        1 | 3 | 6 => normalized.len() - 1,
+        _ => normalized.len(),
+    };
+    let mut spec = Specification::new();
+    spec.symbols.push_str("ABCDEFGHIJKLMNOPQRSTUVWXYZ234567");
+    spec.check_trailing_bits = false;
+    spec.encoding()
+        .expect("base32 specification is valid")
+        .decode(&normalized.as_bytes()[..usable])
+        .map_err(|_| "error: invalid base32 secret".into())
+}
+
+/// RFC 4226 HMAC with dynamic truncation to a 31-bit value
+pub(crate) fn truncate(algorithm: OtpAlgorithm, key: &[u8], counter: u64) -> u32 {
+    dynamic_truncate(&algorithm.mac(key, &counter.to_be_bytes()))
+}
+
+pub(crate) fn dynamic_truncate(digest: &[u8]) -> u32 {
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let bytes: [u8; 4] = digest[offset..offset + 4]
+        .try_into()
+        .expect("offset is at most 15");
+    u32::from_be_bytes(bytes) & 0x7fff_ffff
+}
+
+pub(crate) fn format_numeric(value: u32, digits: u32) -> String {
+    let code = value as u64 % 10u64.pow(digits);
+    format!("{:0width$}", code, width = digits as usize)
+}
+
+pub(crate) fn codes_match(expected: &str, code: &str) -> bool {
+    expected.as_bytes().ct_eq(code.as_bytes()).into()
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum CodeAlphabet {
+    Numeric,
+    Symbols(Vec<char>),
+}
+
+#[wasm_bindgen]
+#[derive(Clone, PartialEq, Debug)]
+/// `OtpFormatter` turns the dynamic-truncation value into a code
+pub struct OtpFormatter {
+    alphabet: CodeAlphabet,
+    length: u32,
+}
+
+#[wasm_bindgen]
+impl OtpFormatter {
+    /// `try_numeric` validate a numeric formatter
+    pub(crate) fn try_numeric(digits: u32) -> Result<OtpFormatter, String> {
+        if !(MIN_DIGITS..=MAX_DIGITS).contains(&digits) {
+            return Err(format!(
+                "error: digits must be between {} and {}",
+                MIN_DIGITS, MAX_DIGITS
+            ));
+        }
+        Ok(OtpFormatter {
+            alphabet: CodeAlphabet::Numeric,
+            length: digits,
+        })
+    }
+
+    /// `try_custom` validate a custom alphabet formatter
+    pub(crate) fn try_custom(alphabet: &str, length: u32) -> Result<OtpFormatter, String> {
+        let symbols: Vec<char> = alphabet.chars().collect();
+        if symbols.len() < 2 {
+            return Err("error: alphabet must have at least 2 symbols".into());
+        }
+        if (1..symbols.len()).any(|i| symbols[..i].contains(&symbols[i])) {
+            return Err("error: alphabet symbols must be unique".into());
+        }
+        if !(1..=MAX_CUSTOM_LENGTH).contains(&length) {
+            return Err(format!(
+                "error: length must be between 1 and {}",
+                MAX_CUSTOM_LENGTH
+            ));
+        }
+        Ok(OtpFormatter {
+            alphabet: CodeAlphabet::Symbols(symbols),
+            length,
+        })
+    }
+
+    /// `numeric` zero-padded decimal code with 6 to 10 digits
+    pub fn numeric(digits: u32) -> Result<OtpFormatter, JsValue> {
+        Self::try_numeric(digits).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `steam` Steam Guard 5-character code
+    pub fn steam() -> OtpFormatter {
+        Self::try_custom(STEAM_ALPHABET, 5).expect("steam alphabet is valid")
+    }
+
+    /// `custom` code of `length` (1 to 16) symbols taken from `alphabet`, least significant first
+    pub fn custom(alphabet: &str, length: u32) -> Result<OtpFormatter, JsValue> {
+        Self::try_custom(alphabet, length).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `length` code length
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// `is_numeric` whether codes are plain decimal digits
+    pub fn is_numeric(&self) -> bool {
+        self.alphabet == CodeAlphabet::Numeric
+    }
+
+    /// `format` format a 31-bit truncated value
+    pub(crate) fn format(&self, value: u32) -> String {
+        match &self.alphabet {
+            CodeAlphabet::Numeric => format_numeric(value, self.length),
+            CodeAlphabet::Symbols(symbols) => {
+                let mut value = value as usize;
+                (0..self.length)
+                    .map(|_| {
+                        let c = symbols[value % symbols.len()];
+                        value /= symbols.len();
+                        c
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone)]
+/// `TotpParams` TOTP generator parameters
+pub struct TotpParams {
+    algorithm: OtpAlgorithm,
+    formatter: OtpFormatter,
+    period: u64,
+    t0: u64,
+}
+
+#[wasm_bindgen]
+impl TotpParams {
+    /// `try_new` validate parameters
+    pub(crate) fn try_new(
+        algorithm: OtpAlgorithm,
+        digits: u32,
+        period: u64,
+        t0: u64,
+    ) -> Result<TotpParams, String> {
+        let formatter = OtpFormatter::try_numeric(digits)?;
+        Self::try_with_formatter(algorithm, formatter, period, t0)
+    }
+
+    /// `try_with_formatter` validate parameters with a custom formatter
+    fn try_with_formatter(
+        algorithm: OtpAlgorithm,
+        formatter: OtpFormatter,
+        period: u64,
+        t0: u64,
+    ) -> Result<TotpParams, String> {
+        if period == 0 {
+            return Err("error: period must be greater than 0".into());
+        }
+        Ok(TotpParams {
+            algorithm,
+            formatter,
+            period,
+            t0,
+        })
+    }
+
+    /// `new` create TOTP parameters
+    ///
+    /// ### Arguments
+    /// * `algorithm` - HMAC algorithm (Sha1, Sha256, Sha512)
+    /// * `digits` - Code length, 6 to 10
+    /// * `period` - Time step in seconds
+    /// * `t0` - Unix time to start counting time steps from
+    pub fn new(
+        algorithm: OtpAlgorithm,
+        digits: u32,
+        period: u64,
+        t0: u64,
+    ) -> Result<TotpParams, JsValue> {
+        Self::try_new(algorithm, digits, period, t0).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `with_formatter` create TOTP parameters with a custom code formatter
+    ///
+    /// ### Arguments
+    /// * `algorithm` - HMAC algorithm (Sha1, Sha256, Sha512)
+    /// * `formatter` - Code formatter (numeric, Steam or custom alphabet)
+    /// * `period` - Time step in seconds
+    /// * `t0` - Unix time to start counting time steps from
+    pub fn with_formatter(
+        algorithm: OtpAlgorithm,
+        formatter: &OtpFormatter,
+        period: u64,
+        t0: u64,
+    ) -> Result<TotpParams, JsValue> {
+        Self::try_with_formatter(algorithm, formatter.clone(), period, t0)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `standard` SHA-1, 6 digits, 30 seconds, T0 = 0
+    pub fn standard() -> TotpParams {
+        TotpParams {
+            algorithm: OtpAlgorithm::Sha1,
+            formatter: OtpFormatter {
+                alphabet: CodeAlphabet::Numeric,
+                length: HOTP_DIGITS,
+            },
+            period: 30,
+            t0: 0,
+        }
+    }
+
+    /// `steam` Steam Guard: SHA-1, 5 Steam symbols, 30 seconds, T0 = 0
+    pub fn steam() -> TotpParams {
+        TotpParams {
+            formatter: OtpFormatter::steam(),
+            ..Self::standard()
+        }
+    }
+
+    /// `algorithm` HMAC algorithm
+    pub fn algorithm(&self) -> OtpAlgorithm {
+        self.algorithm
+    }
+
+    /// `digits` code length
+    pub fn digits(&self) -> u32 {
+        self.formatter.length
+    }
+
+    /// `formatter` code formatter
+    pub fn formatter(&self) -> OtpFormatter {
+        self.formatter.clone()
+    }
+
+    /// `period` time step in seconds
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+
+    /// `t0` epoch offset in seconds
+    pub fn t0(&self) -> u64 {
+        self.t0
+    }
+
+    /// `time_step` time-step counter for the given timestamp
+    pub(crate) fn time_step(&self, timestamp: u64) -> Result<u64, String> {
+        timestamp
+            .checked_sub(self.t0)
+            .map(|elapsed| elapsed / self.period)
+            .ok_or_else(|| "error: timestamp is before t0".into())
+    }
+
+    /// `code_at` generate the code for a time-step counter
+    pub(crate) fn code_at(&self, key: &[u8], step: u64) -> String {
+        self.formatter.format(truncate(self.algorithm, key, step))
+    }
+}
+
+#[wasm_bindgen]
+/// `HotpVerification` result of a HOTP verification
+pub struct HotpVerification {
+    valid: bool,
+    next_counter: u64,
+}
+
+#[wasm_bindgen]
+impl HotpVerification {
+    /// `valid` whether the code matched a counter inside the window
+    pub fn valid(&self) -> bool {
+        self.valid
+    }
+
+    /// `next_counter` counter to persist for the next verification
+    pub fn next_counter(&self) -> u64 {
+        self.next_counter
+    }
+}
+
+#[wasm_bindgen]
+/// `TotpVerification` result of a TOTP verification
+pub struct TotpVerification {
+    valid: bool,
+    offset: i64,
+    last_step: Option<u64>,
+}
+
+#[wasm_bindgen]
+impl TotpVerification {
+    /// `valid` whether the code matched an unused time step inside the window
+    pub fn valid(&self) -> bool {
+        self.valid
+    }
+
+    /// `offset` matched time step relative to the current one (0 if invalid)
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// `last_step` last accepted time step to persist for replay protection
+    pub fn last_step(&self) -> Option<u64> {
+        self.last_step
+    }
+}
+
+#[wasm_bindgen]
+/// `TotpEnrollment` everything needed to enroll a TOTP authenticator
+pub struct TotpEnrollment {
+    secret: String,
+    uri: String,
+    qr_packed: Vec<u8>,
+    qr_svg: String,
+    code: String,
+}
+
+#[wasm_bindgen]
+impl TotpEnrollment {
+    /// `secret` Base32 secret (no padding) to store server-side
+    pub fn secret(&self) -> String {
+        self.secret.clone()
+    }
+
+    /// `uri` otpauth:// URI encoded in the QR code
+    pub fn uri(&self) -> String {
+        self.uri.clone()
+    }
+
+    /// `qr_packed` QR matrix in the `QRCode::raw` format: [width, is_micro, module_data...]
+    pub fn qr_packed(&self) -> Vec<u8> {
+        self.qr_packed.clone()
+    }
+
+    /// `qr_svg` QR code as a standalone SVG document
+    pub fn qr_svg(&self) -> String {
+        self.qr_svg.clone()
+    }
+
+    /// `code` current code, for the user to confirm the enrollment
+    pub fn code(&self) -> String {
+        self.code.clone()
+    }
+}
+
 #[wasm_bindgen]
 /// `OTPAuth` otp auth
 pub struct OTPAuth {}
 
 #[wasm_bindgen]
 impl OTPAuth {
-    /// `generate_code` generate TOTP code as 6-digit string
-    pub fn generate_code(secret: &str, timestamp: u64, period: u64) -> String {
-        match TOTP::from_base32(secret) {
-            Some(totp) => {
-                let code = totp.generate(period, timestamp);
-                format!("{:06}", code)
+    /// `try_generate_totp` TOTP code for the time step `offset` steps away from `timestamp`
+    pub(crate) fn try_generate_totp(
+        secret: &str,
+        timestamp: u64,
+        params: &TotpParams,
+        offset: i64,
+    ) -> Result<String, String> {
+        let key = decode_secret(secret)?;
+        let step = params
+            .time_step(timestamp)?
+            .checked_add_signed(offset)
+            .ok_or("error: time step out of range")?;
+        Ok(params.code_at(&key, step))
+    }
+
+    /// `generate_code` generate TOTP code as 6-digit string (HMAC-SHA1, T0 = 0)
+    ///
+    /// ### Arguments
+    /// * `secret` - Base32 secret
+    /// * `timestamp` - Unix time in seconds
+    /// * `period` - Time step in seconds
+    pub fn generate_code(secret: &str, timestamp: u64, period: u64) -> Result<String, JsValue> {
+        TotpParams::try_new(OtpAlgorithm::Sha1, MIN_DIGITS, period, 0)
+            .and_then(|params| Self::try_generate_totp(secret, timestamp, &params, 0))
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `generate_totp` generate TOTP code (RFC 6238) with custom parameters
+    ///
+    /// ### Arguments
+    /// * `secret` - Base32 secret
+    /// * `timestamp` - Unix time in seconds
+    /// * `params` - Algorithm, digits, period and T0
+    pub fn generate_totp(
+        secret: &str,
+        timestamp: u64,
+        params: &TotpParams,
+    ) -> Result<String, JsValue> {
+        Self::try_generate_totp(secret, timestamp, params, 0).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `generate_totp_offset` generate the code for a neighbouring time step
+    ///
+    /// ### Arguments
+    /// * `secret` - Base32 secret
+    /// * `timestamp` - Unix time in seconds
+    /// * `params` - Algorithm, digits, period and T0
+    /// * `offset` - Steps relative to the current one (-1 previous, 1 next)
+    pub fn generate_totp_offset(
+        secret: &str,
+        timestamp: u64,
+        params: &TotpParams,
+        offset: i64,
+    ) -> Result<String, JsValue> {
+        Self::try_generate_totp(secret, timestamp, params, offset)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `current_counter` time step counter `(timestamp - t0) / period`
+    pub fn current_counter(timestamp: u64, params: &TotpParams) -> Result<u64, JsValue> {
+        params
+            .time_step(timestamp)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `try_seconds_remaining` start of the next step minus `timestamp`, without overflowing
+    pub(crate) fn try_seconds_remaining(
+        timestamp: u64,
+        params: &TotpParams,
+    ) -> Result<u64, String> {
+        let step = params.time_step(timestamp)?;
+        step.checked_add(1)
+            .and_then(|next| next.checked_mul(params.period()))
+            .and_then(|offset| offset.checked_add(params.t0()))
+            .map(|next_start| next_start - timestamp)
+            .ok_or_else(|| "error: next time step is out of range".into())
+    }
+
+    /// `seconds_remaining` seconds until the next time step (1..=period)
+    pub fn seconds_remaining(timestamp: u64, params: &TotpParams) -> Result<u64, JsValue> {
+        Self::try_seconds_remaining(timestamp, params).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `verify_totp` verify TOTP code with clock-drift window and replay protection
+    ///
+    /// ### Arguments
+    /// * `secret` - Base32 secret
+    /// * `code` - Code entered by the user
+    /// * `timestamp` - Unix time in seconds
+    /// * `params` - Algorithm, digits, period and T0
+    /// * `past_steps` - Number of earlier time steps to accept, at most 10
+    /// * `future_steps` - Number of later time steps to accept, at most 10
+    /// * `last_step` - Last accepted time step; it and earlier steps are rejected
+    ///
+    /// ### Returns
+    /// TotpVerification with the matched offset and the last step to persist
+    pub fn verify_totp(
+        secret: &str,
+        code: &str,
+        timestamp: u64,
+        params: &TotpParams,
+        past_steps: u32,
+        future_steps: u32,
+        last_step: Option<u64>,
+    ) -> Result<TotpVerification, JsValue> {
+        Self::try_verify_totp(
+            secret,
+            code,
+            timestamp,
+            params,
+            past_steps,
+            future_steps,
+            last_step,
+        )
+        .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `try_verify_totp` drift-window verification; both skews are capped
+    pub(crate) fn try_verify_totp(
+        secret: &str,
+        code: &str,
+        timestamp: u64,
+        params: &TotpParams,
+        past_steps: u32,
+        future_steps: u32,
+        last_step: Option<u64>,
+    ) -> Result<TotpVerification, String> {
+        if past_steps > MAX_TOTP_SKEW || future_steps > MAX_TOTP_SKEW {
+            return Err(format!(
+                "error: TOTP skew must be at most {} steps",
+                MAX_TOTP_SKEW
+            ));
+        }
+        let key = decode_secret(secret)?;
+        let current = params.time_step(timestamp)?;
+
+        // every candidate is compared so timing does not reveal the matched step
+        let mut matched: Option<(i64, u64)> = None;
+        for offset in -(past_steps as i64)..=future_steps as i64 {
+            let Some(step) = current.checked_add_signed(offset) else {
+                continue;
+            };
+            let fresh = last_step.is_none_or(|last| step > last);
+            if codes_match(&params.code_at(&key, step), code) && fresh && matched.is_none() {
+                matched = Some((offset, step));
             }
-            None => {
-                #[cfg(debug_assertions)]
-                eprintln!("Failed to parse Base32 secret: {}", secret);
-                "000000".to_string()
+        }
+
+        Ok(match matched {
+            Some((offset, step)) => TotpVerification {
+                valid: true,
+                offset,
+                last_step: Some(step),
+            },
+            None => TotpVerification {
+                valid: false,
+                offset: 0,
+                last_step,
+            },
+        })
+    }
+
+    /// `generate_from_uri` generate the current code described by an otpauth:// URI
+    ///
+    /// ### Arguments
+    /// * `uri` - otpauth://totp/... or otpauth://hotp/... URI
+    /// * `timestamp` - Unix time in seconds (ignored for HOTP, which uses the URI counter)
+    pub fn generate_from_uri(uri: &str, timestamp: u64) -> Result<String, JsValue> {
+        Ok(OtpAuthUri::parse(uri)?.generate(timestamp))
+    }
+
+    /// `enroll` create a new TOTP secret with its otpauth:// URI and QR code
+    ///
+    /// ### Arguments
+    /// * `issuer` - Service name shown in the authenticator
+    /// * `account` - Account name, e.g. an email address
+    /// * `params` - Algorithm, digits and period (T0 must be 0)
+    /// * `timestamp` - Unix time in seconds for the confirmation code
+    ///
+    /// ### Returns
+    /// TotpEnrollment with the secret, URI, QR matrix and SVG, and the current code
+    pub fn enroll(
+        issuer: Option<String>,
+        account: &str,
+        params: &TotpParams,
+        timestamp: u64,
+    ) -> Result<TotpEnrollment, JsValue> {
+        let key = SafeRandom::gen_bytes(params.algorithm().output_len()).raw();
+        let secret = BASE32_NOPAD.encode(&key);
+        let uri = OtpAuthUri::new_totp(issuer, account, &secret, params)?.to_uri();
+        let step = params
+            .time_step(timestamp)
+            .map_err(|e| JsValue::from_str(&e))?;
+
+        Ok(TotpEnrollment {
+            qr_packed: QRCode::raw_default(&uri)?,
+            qr_svg: QRCode::svg_default(&uri)?,
+            code: params.code_at(&key, step),
+            secret,
+            uri,
+        })
+    }
+
+    /// `generate_hotp` generate HOTP code (RFC 4226) as 6-digit string
+    pub fn generate_hotp(secret: &str, counter: u64) -> Result<String, JsValue> {
+        let key = decode_secret(secret).map_err(|e| JsValue::from_str(&e))?;
+        Ok(format_numeric(
+            truncate(OtpAlgorithm::Sha1, &key, counter),
+            HOTP_DIGITS,
+        ))
+    }
+
+    /// `verify_hotp` verify HOTP code against counters `counter..=counter + window`
+    ///
+    /// ### Arguments
+    /// * `secret` - Base32 secret
+    /// * `code` - Code entered by the user
+    /// * `counter` - Last persisted counter
+    /// * `window` - Number of counters to look ahead, at most 100
+    ///
+    /// ### Returns
+    /// HotpVerification with the counter to persist (matched counter + 1, or `counter` if invalid)
+    pub fn verify_hotp(
+        secret: &str,
+        code: &str,
+        counter: u64,
+        window: u64,
+    ) -> Result<HotpVerification, JsValue> {
+        Self::try_verify_hotp(secret, code, counter, window).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `try_verify_hotp` look-ahead verification; a match at `u64::MAX` has no next counter
+    pub(crate) fn try_verify_hotp(
+        secret: &str,
+        code: &str,
+        counter: u64,
+        window: u64,
+    ) -> Result<HotpVerification, String> {
+        if window > MAX_HOTP_WINDOW {
+            return Err(format!(
+                "error: HOTP window must be at most {}",
+                MAX_HOTP_WINDOW
+            ));
+        }
+        let key = decode_secret(secret)?;
+        for candidate in counter..=counter.saturating_add(window) {
+            let expected =
+                format_numeric(truncate(OtpAlgorithm::Sha1, &key, candidate), HOTP_DIGITS);
+            if codes_match(&expected, code) {
+                let next_counter = candidate
+                    .checked_add(1)
+                    .ok_or("error: HOTP counter is exhausted")?;
+                return Ok(HotpVerification {
+                    valid: true,
+                    next_counter,
+                });
             }
         }
+        Ok(HotpVerification {
+            valid: false,
+            next_counter: counter,
+        })
     }
 }
 
@@ -33,7 +702,300 @@ fn totp_test() {
 
     let input = "us3whsg7x5kapv27vanwkqhf3sh3hull";
     let secret = input.replace(" ", "").to_uppercase();
-    let result = OTPAuth::generate_code(&secret, timestamp, 30);
+    let result = OTPAuth::generate_code(&secret, timestamp, 30).unwrap();
     println!("totp: {:?}", result);
-    assert_ne!("000000", result);
+    assert_eq!(6, result.len());
+
+    let rfc = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    assert_eq!("287082", OTPAuth::generate_code(rfc, 59, 30).unwrap());
+
+    // bad secrets and periods are errors instead of a real-looking code
+    let params = TotpParams::standard();
+    assert!(OTPAuth::try_generate_totp("not base32!", 59, &params, 0).is_err());
+    assert!(OTPAuth::try_generate_totp("JBSWY3DPEHPK3PX!", 59, &params, 0).is_err());
+
+    // secrets with trailing bits or a partial final group, as the otpauth crate decoded them
+    for (secret, code) in [
+        ("JBSWY3DPEHPK3PXP", "996554"),
+        ("JBSWY3DPEHPK3PXPAB", "996554"),
+        ("JBSWY3DPEHPK3PXPABC", "996554"),
+        ("JBSWY3DPEHPK3PXQ7", "797611"),
+        ("jbswy3dpehpk3pxq7", "797611"),
+    ] {
+        assert_eq!(code, OTPAuth::generate_code(secret, 59, 30).unwrap());
+    }
+    assert_eq!(
+        decode_secret("JBSWY3DPEHPK3PXQ").unwrap(),
+        decode_secret("JBSWY3DPEHPK3PXQ7").unwrap()
+    );
+    assert!(OTPAuth::try_generate_totp(rfc, 0, &params, -1).is_err());
+    assert!(TotpParams::try_new(OtpAlgorithm::Sha1, 6, 0, 0).is_err());
+}
+
+#[test]
+fn totp_time_step_test() {
+    let rfc = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    let params = TotpParams::new(OtpAlgorithm::Sha1, 8, 30, 0).unwrap();
+
+    assert_eq!(
+        37037036,
+        OTPAuth::current_counter(1111111109, &params).unwrap()
+    );
+    assert_eq!(1, OTPAuth::seconds_remaining(1111111109, &params).unwrap());
+    assert_eq!(30, OTPAuth::seconds_remaining(1111111110, &params).unwrap());
+    assert_eq!(
+        "07081804",
+        OTPAuth::generate_totp_offset(rfc, 1111111139, &params, -1).unwrap()
+    );
+    assert_eq!(
+        "14050471",
+        OTPAuth::generate_totp_offset(rfc, 1111111109, &params, 1).unwrap()
+    );
+
+    let shifted = TotpParams::new(OtpAlgorithm::Sha1, 6, 60, 15).unwrap();
+    assert_eq!(0, OTPAuth::current_counter(74, &shifted).unwrap());
+    assert_eq!(1, OTPAuth::seconds_remaining(74, &shifted).unwrap());
+    assert_eq!(60, OTPAuth::seconds_remaining(75, &shifted).unwrap());
+
+    // the next step would start past u64::MAX
+    let huge = TotpParams::new(OtpAlgorithm::Sha1, 6, u64::MAX / 2 + 1, 0).unwrap();
+    assert!(OTPAuth::try_seconds_remaining(u64::MAX - 1, &huge).is_err());
+    let late = TotpParams::new(OtpAlgorithm::Sha1, 6, 30, u64::MAX - 10).unwrap();
+    assert!(OTPAuth::try_seconds_remaining(u64::MAX, &late).is_err());
+    assert!(OTPAuth::try_seconds_remaining(0, &late).is_err());
+}
+
+#[test]
+fn hotp_rfc4226_test() {
+    // RFC 4226 Appendix D, secret "12345678901234567890"
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    let expected = [
+        "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583", "399871",
+        "520489",
+    ];
+    for (counter, code) in expected.iter().enumerate() {
+        let result = OTPAuth::generate_hotp(secret, counter as u64).unwrap();
+        println!("hotp {}: {:?}", counter, result);
+        assert_eq!(*code, result);
+    }
+}
+
+#[test]
+fn hotp_verify_test() {
+    let secret = "gezd gnbv gy3t qojq gezd gnbv gy3t qojq";
+
+    let result = OTPAuth::verify_hotp(secret, "969429", 1, 5).unwrap();
+    assert!(result.valid());
+    assert_eq!(4, result.next_counter());
+
+    // already consumed counter
+    let result = OTPAuth::verify_hotp(secret, "969429", 4, 5).unwrap();
+    assert!(!result.valid());
+    assert_eq!(4, result.next_counter());
+
+    // outside the look-ahead window
+    let result = OTPAuth::verify_hotp(secret, "520489", 0, 5).unwrap();
+    assert!(!result.valid());
+    assert_eq!(0, result.next_counter());
+
+    // the last counter cannot be advanced past u64::MAX
+    let last = OTPAuth::generate_hotp(secret, u64::MAX).unwrap();
+    assert!(OTPAuth::try_verify_hotp(secret, &last, u64::MAX - 1, 5).is_err());
+    let other = if last == "000000" { "111111" } else { "000000" };
+    let result = OTPAuth::try_verify_hotp(secret, other, u64::MAX, 5).unwrap();
+    assert!(!result.valid());
+    assert_eq!(u64::MAX, result.next_counter());
+
+    // the look-ahead window is capped
+    assert!(OTPAuth::try_verify_hotp(secret, other, 0, 100).is_ok());
+    assert!(OTPAuth::try_verify_hotp(secret, other, 0, 101).is_err());
+    assert!(OTPAuth::try_verify_hotp(secret, other, 0, u64::MAX).is_err());
+}
+
+#[test]
+fn totp_rfc6238_test() {
+    // RFC 6238 Appendix B, 8 digits, 30 seconds
+    let sha1 = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    let sha256 = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA";
+    let sha512 = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNA";
+    let vectors: [(u64, &str, &str, &str); 6] = [
+        (59, "94287082", "46119246", "90693936"),
+        (1111111109, "07081804", "68084774", "25091201"),
+        (1111111111, "14050471", "67062674", "99943326"),
+        (1234567890, "89005924", "91819424", "93441116"),
+        (2000000000, "69279037", "90698825", "38618901"),
+        (20000000000, "65353130", "77737706", "47863826"),
+    ];
+
+    let p1 = TotpParams::new(OtpAlgorithm::Sha1, 8, 30, 0).unwrap();
+    let p256 = TotpParams::new(OtpAlgorithm::Sha256, 8, 30, 0).unwrap();
+    let p512 = TotpParams::new(OtpAlgorithm::Sha512, 8, 30, 0).unwrap();
+    for (time, c1, c256, c512) in vectors {
+        let r1 = OTPAuth::generate_totp(sha1, time, &p1).unwrap();
+        let r256 = OTPAuth::generate_totp(sha256, time, &p256).unwrap();
+        let r512 = OTPAuth::generate_totp(sha512, time, &p512).unwrap();
+        println!("totp {}: {} {} {}", time, r1, r256, r512);
+        assert_eq!(c1, r1);
+        assert_eq!(c256, r256);
+        assert_eq!(c512, r512);
+    }
+}
+
+#[test]
+fn totp_params_test() {
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    // T0 shifts the time-step counter
+    let shifted = TotpParams::new(OtpAlgorithm::Sha1, 8, 30, 1000).unwrap();
+    let result = OTPAuth::generate_totp(secret, 1059, &shifted).unwrap();
+    assert_eq!("94287082", result);
+
+    // standard parameters match the 6-digit generator
+    let standard = OTPAuth::generate_totp(secret, 59, &TotpParams::standard()).unwrap();
+    assert_eq!(OTPAuth::generate_code(secret, 59, 30).unwrap(), standard);
+
+    let ten = TotpParams::new(OtpAlgorithm::Sha1, 10, 30, 0).unwrap();
+    let result = OTPAuth::generate_totp(secret, 59, &ten).unwrap();
+    println!("totp 10 digits: {}", result);
+    assert_eq!(10, result.len());
+    assert!(result.ends_with("94287082"));
+
+    assert!(TotpParams::try_new(OtpAlgorithm::Sha1, 5, 30, 0).is_err());
+    assert!(TotpParams::try_new(OtpAlgorithm::Sha1, 11, 30, 0).is_err());
+    assert!(TotpParams::try_new(OtpAlgorithm::Sha1, 6, 0, 0).is_err());
+}
+
+#[test]
+fn totp_verify_test() {
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    let params = TotpParams::new(OtpAlgorithm::Sha1, 8, 30, 0).unwrap();
+
+    // code for step 37037036 (time 1111111109) checked one step later
+    let result = OTPAuth::verify_totp(secret, "07081804", 1111111125, &params, 1, 1, None).unwrap();
+    println!(
+        "totp verify: valid={} offset={} last_step={:?}",
+        result.valid(),
+        result.offset(),
+        result.last_step()
+    );
+    assert!(result.valid());
+    assert_eq!(-1, result.offset());
+    assert_eq!(Some(37037036), result.last_step());
+
+    // replaying the same step is rejected
+    let replay = OTPAuth::verify_totp(
+        secret,
+        "07081804",
+        1111111125,
+        &params,
+        1,
+        1,
+        result.last_step(),
+    )
+    .unwrap();
+    assert!(!replay.valid());
+    assert_eq!(Some(37037036), replay.last_step());
+
+    // outside the allowed skew
+    let result = OTPAuth::verify_totp(secret, "07081804", 1111111125, &params, 0, 1, None).unwrap();
+    assert!(!result.valid());
+    assert_eq!(None, result.last_step());
+
+    let result = OTPAuth::verify_totp(secret, "0708180", 1111111109, &params, 1, 1, None).unwrap();
+    assert!(!result.valid());
+
+    // the skew in either direction is capped
+    let verify = |past, future| {
+        OTPAuth::try_verify_totp(secret, "07081804", 1111111125, &params, past, future, None)
+    };
+    assert!(verify(10, 10).unwrap().valid());
+    assert!(verify(11, 0).is_err());
+    assert!(verify(0, 11).is_err());
+    assert!(verify(u32::MAX, u32::MAX).is_err());
+}
+
+#[test]
+fn totp_from_uri_test() {
+    let uri = "otpauth://totp/RFC:6238?secret=gezdgnbvgy3tqojqgezdgnbvgy3tqojq&issuer=RFC&digits=8";
+    let result = OTPAuth::generate_from_uri(uri, 1111111109).unwrap();
+    println!("totp from uri: {}", result);
+    assert_eq!("07081804", result);
+}
+
+#[test]
+fn totp_formatter_test() {
+    // steamguard-cli test secret "zvIayp3JPvtvX/QGHqsqKBk/44s=" in Base32
+    let secret = "Z3ZBVSU5ZE7PW3276QDB5KZKFAMT7Y4L";
+    let result = OTPAuth::generate_totp(secret, 1616374841, &TotpParams::steam()).unwrap();
+    println!("steam: {}", result);
+    assert_eq!("2F9J5", result);
+
+    let rfc = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    let numeric = OtpFormatter::numeric(8).unwrap();
+    let params = TotpParams::with_formatter(OtpAlgorithm::Sha1, &numeric, 30, 0).unwrap();
+    assert_eq!(
+        "94287082",
+        OTPAuth::generate_totp(rfc, 59, &params).unwrap()
+    );
+
+    // a ten-symbol alphabet of digits matches the numeric code reversed
+    let custom = OtpFormatter::custom("0123456789", 8).unwrap();
+    let params = TotpParams::with_formatter(OtpAlgorithm::Sha1, &custom, 30, 0).unwrap();
+    let result = OTPAuth::generate_totp(rfc, 59, &params).unwrap();
+    println!("custom: {}", result);
+    assert_eq!("28078249", result);
+
+    let letters = OtpFormatter::custom("abcdefghijklmnopqrstuvwxyz", 8).unwrap();
+    let params = TotpParams::with_formatter(OtpAlgorithm::Sha256, &letters, 30, 0).unwrap();
+    let result = OTPAuth::generate_totp(rfc, 59, &params).unwrap();
+    println!("letters: {}", result);
+    assert!(result.chars().all(|c| c.is_ascii_lowercase()));
+
+    assert!(OtpFormatter::try_custom("a", 5).is_err());
+    assert!(OtpFormatter::try_custom("aba", 5).is_err());
+    assert!(OtpFormatter::try_custom("ab", 0).is_err());
+    assert!(OtpFormatter::try_custom("ab", 16).is_ok());
+    assert!(OtpFormatter::try_custom("ab", 17).is_err());
+    assert!(OtpFormatter::try_custom("ab", u32::MAX).is_err());
+}
+
+#[test]
+fn totp_enroll_test() {
+    let params = TotpParams::new(OtpAlgorithm::Sha256, 8, 30, 0).unwrap();
+    let enrollment = OTPAuth::enroll(
+        Some("ACME Co".into()),
+        "john@example.com",
+        &params,
+        1700000000,
+    )
+    .unwrap();
+    println!("enroll uri: {}", enrollment.uri());
+
+    assert_eq!(52, enrollment.secret().len());
+    assert!(
+        enrollment
+            .uri()
+            .starts_with("otpauth://totp/ACME%20Co:john%40example.com?secret=")
+    );
+    assert!(enrollment.uri().contains(&enrollment.secret()));
+    assert!(enrollment.qr_svg().contains("<svg"));
+    assert_eq!(
+        OTPAuth::generate_totp(&enrollment.secret(), 1700000000, &params).unwrap(),
+        enrollment.code()
+    );
+
+    // the QR matrix decodes back to the same URI
+    let packed = enrollment.qr_packed();
+    let width = packed[0] as usize;
+    let gray: Vec<u8> = (0..width * width)
+        .map(|i| {
+            if packed[2 + i / 8] & (1 << (7 - i % 8)) != 0 {
+                0
+            } else {
+                255
+            }
+        })
+        .collect();
+    let decoded = QRCode::decode_luma(&gray, width as u32, width as u32).unwrap();
+    assert_eq!(vec![enrollment.uri()], decoded);
 }
@@ -0,0 +1,805 @@
+use crate::tools::otp::{OtpAlgorithm, TotpParams, decode_secret, format_numeric, truncate};
+use crate::tools::qrcode::QRCode;
+use crate::tools::random::SafeRandom;
+use data_encoding::{BASE32_NOPAD, BASE64, BASE64_NOPAD};
+use wasm_bindgen::prelude::*;
+
+const SCHEME: &str = "otpauth://";
+
+fn percent_decode(input: &str, plus_as_space: bool) -> Result<String, String> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or("error: invalid percent-encoding")?;
+                out.push(hex);
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).map_err(|_| "error: invalid utf-8 in uri".into())
+}
+
+fn percent_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for &b in input.as_bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// decode and re-encode so lowercase, spaced or padded secrets become canonical Base32
+fn normalize_secret(secret: &str) -> Result<String, String> {
+    let bytes = decode_secret(secret)?;
+    if bytes.is_empty() {
+        return Err("error: secret is required".into());
+    }
+    Ok(BASE32_NOPAD.encode(&bytes))
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// OTP type of an otpauth URI
+pub enum OtpKind {
+    /// Time-based (RFC 6238)
+    Totp = 0,
+    /// Counter-based (RFC 4226)
+    Hotp = 1,
+}
+
+impl OtpKind {
+    fn name(&self) -> &'static str {
+        match self {
+            OtpKind::Totp => "totp",
+            OtpKind::Hotp => "hotp",
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone)]
+/// `OtpAuthUri` typed otpauth:// URI
+pub struct OtpAuthUri {
+    kind: OtpKind,
+    issuer: Option<String>,
+    account: String,
+    secret: String,
+    params: TotpParams,
+    counter: u64,
+}
+
+#[wasm_bindgen]
+impl OtpAuthUri {
+    /// `try_new` validate and normalize the URI fields
+    pub(crate) fn try_new(
+        kind: OtpKind,
+        issuer: Option<String>,
+        account: &str,
+        secret: &str,
+        params: TotpParams,
+        counter: u64,
+    ) -> Result<OtpAuthUri, String> {
+        let issuer = issuer.filter(|i| !i.is_empty());
+        if issuer.as_deref().is_some_and(|i| i.contains(':')) {
+            return Err("error: issuer must not contain ':'".into());
+        }
+        if account.is_empty() {
+            return Err("error: account is required".into());
+        }
+        if params.t0() != 0 {
+            return Err("error: otpauth uri does not support a custom t0".into());
+        }
+        if !params.formatter().is_numeric() {
+            return Err("error: otpauth uri only supports numeric codes".into());
+        }
+        Ok(OtpAuthUri {
+            kind,
+            issuer,
+            account: account.to_string(),
+            secret: normalize_secret(secret)?,
+            params,
+            counter,
+        })
+    }
+
+    /// `try_parse` parse an otpauth:// URI
+    pub(crate) fn try_parse(uri: &str) -> Result<OtpAuthUri, String> {
+        let scheme = uri.get(..SCHEME.len()).unwrap_or_default();
+        if !scheme.eq_ignore_ascii_case(SCHEME) {
+            return Err("error: uri must start with otpauth://".into());
+        }
+        let rest = &uri[SCHEME.len()..];
+
+        let (path, query) = rest.split_once('?').ok_or("error: secret is required")?;
+        let (kind, label) = path.split_once('/').ok_or("error: label is required")?;
+        let kind = match kind.to_lowercase().as_str() {
+            "totp" => OtpKind::Totp,
+            "hotp" => OtpKind::Hotp,
+            _ => return Err(format!("error: unsupported otp type: {}", kind)),
+        };
+
+        let label = percent_decode(label, false)?;
+        let (label_issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.to_string()), account.trim_start()),
+            None => (None, label.as_str()),
+        };
+
+        let mut secret: Option<String> = None;
+        let mut issuer: Option<String> = None;
+        let mut algorithm: Option<String> = None;
+        let mut digits: Option<String> = None;
+        let mut period: Option<String> = None;
+        let mut counter: Option<String> = None;
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let slot = match key {
+                "secret" => &mut secret,
+                "issuer" => &mut issuer,
+                "algorithm" => &mut algorithm,
+                "digits" => &mut digits,
+                "period" => &mut period,
+                "counter" => &mut counter,
+                _ => continue,
+            };
+            if slot.is_some() {
+                return Err(format!("error: duplicate parameter: {}", key));
+            }
+            *slot = Some(percent_decode(value, true)?);
+        }
+
+        let issuer = match (label_issuer, issuer) {
+            (Some(a), Some(b)) if a != b => {
+                return Err("error: issuer parameter does not match label".into());
+            }
+            (a, b) => b.or(a),
+        };
+        let secret = secret.ok_or("error: secret is required")?;
+        let algorithm = match algorithm {
+            Some(name) => OtpAlgorithm::from_name(&name)
+                .ok_or_else(|| format!("error: unsupported algorithm: {}", name))?,
+            None => OtpAlgorithm::Sha1,
+        };
+        let digits = match digits {
+            Some(d) => d.parse().map_err(|_| "error: invalid digits")?,
+            None => 6,
+        };
+        let period = match period {
+            Some(p) => p.parse().map_err(|_| "error: invalid period")?,
+            None => 30,
+        };
+        let counter = match (kind, counter) {
+            (_, Some(c)) => c.parse().map_err(|_| "error: invalid counter")?,
+            (OtpKind::Hotp, None) => return Err("error: counter is required for hotp".into()),
+            (OtpKind::Totp, None) => 0,
+        };
+
+        let params = TotpParams::try_new(algorithm, digits, period, 0)?;
+        Self::try_new(kind, issuer, account, &secret, params, counter)
+    }
+
+    /// `parse` parse an otpauth:// URI, percent-decoding the label and normalizing the secret
+    pub fn parse(uri: &str) -> Result<OtpAuthUri, JsValue> {
+        Self::try_parse(uri).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `new_totp` build a TOTP URI (params.t0 must be 0)
+    pub fn new_totp(
+        issuer: Option<String>,
+        account: &str,
+        secret: &str,
+        params: &TotpParams,
+    ) -> Result<OtpAuthUri, JsValue> {
+        Self::try_new(OtpKind::Totp, issuer, account, secret, params.clone(), 0)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `new_hotp` build a HOTP URI
+    pub fn new_hotp(
+        issuer: Option<String>,
+        account: &str,
+        secret: &str,
+        algorithm: OtpAlgorithm,
+        digits: u32,
+        counter: u64,
+    ) -> Result<OtpAuthUri, JsValue> {
+        TotpParams::try_new(algorithm, digits, 30, 0)
+            .and_then(|params| {
+                Self::try_new(OtpKind::Hotp, issuer, account, secret, params, counter)
+            })
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `to_uri` serialize to an otpauth:// URI
+    pub fn to_uri(&self) -> String {
+        let label = match &self.issuer {
+            Some(issuer) => format!(
+                "{}:{}",
+                percent_encode(issuer),
+                percent_encode(&self.account)
+            ),
+            None => percent_encode(&self.account),
+        };
+        let mut uri = format!(
+            "{}{}/{}?secret={}",
+            SCHEME,
+            self.kind.name(),
+            label,
+            self.secret
+        );
+        if let Some(issuer) = &self.issuer {
+            uri.push_str(&format!("&issuer={}", percent_encode(issuer)));
+        }
+        uri.push_str(&format!(
+            "&algorithm={}&digits={}",
+            self.params.algorithm().name(),
+            self.params.digits()
+        ));
+        match self.kind {
+            OtpKind::Totp => uri.push_str(&format!("&period={}", self.params.period())),
+            OtpKind::Hotp => uri.push_str(&format!("&counter={}", self.counter)),
+        }
+        uri
+    }
+
+    /// `generate` current code: TOTP at `timestamp`, HOTP at the URI counter
+    pub fn generate(&self, timestamp: u64) -> String {
+        let key = decode_secret(&self.secret).expect("secret is validated on construction");
+        let step = match self.kind {
+            OtpKind::Totp => timestamp / self.params.period(),
+            OtpKind::Hotp => self.counter,
+        };
+        format_numeric(
+            truncate(self.params.algorithm(), &key, step),
+            self.params.digits(),
+        )
+    }
+
+    /// `kind` TOTP or HOTP
+    pub fn kind(&self) -> OtpKind {
+        self.kind
+    }
+
+    /// `issuer` issuer from the parameter or label prefix
+    pub fn issuer(&self) -> Option<String> {
+        self.issuer.clone()
+    }
+
+    /// `account` account name
+    pub fn account(&self) -> String {
+        self.account.clone()
+    }
+
+    /// `secret` normalized Base32 secret (uppercase, no padding)
+    pub fn secret(&self) -> String {
+        self.secret.clone()
+    }
+
+    /// `algorithm` HMAC algorithm
+    pub fn algorithm(&self) -> OtpAlgorithm {
+        self.params.algorithm()
+    }
+
+    /// `digits` code length
+    pub fn digits(&self) -> u32 {
+        self.params.digits()
+    }
+
+    /// `period` TOTP time step in seconds
+    pub fn period(&self) -> u64 {
+        self.params.period()
+    }
+
+    /// `counter` HOTP counter
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// `params` TOTP parameters for use with `OTPAuth`
+    pub fn params(&self) -> TotpParams {
+        self.params.clone()
+    }
+}
+
+const MIGRATION_PREFIX: &str = "otpauth-migration://offline?";
+
+enum ProtoValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos).ok_or("error: truncated protobuf varint")?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("error: protobuf varint too long".into())
+}
+
+/// read the fields of a protobuf message, skipping fixed-width ones
+fn read_fields(data: &[u8]) -> Result<Vec<(u64, ProtoValue<'_>)>, String> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let key = read_varint(data, &mut pos)?;
+        let skip = match key & 0x07 {
+            0 => {
+                fields.push((key >> 3, ProtoValue::Varint(read_varint(data, &mut pos)?)));
+                0
+            }
+            2 => {
+                let len = read_varint(data, &mut pos)? as usize;
+                let end = pos
+                    .checked_add(len)
+                    .filter(|&end| end <= data.len())
+                    .ok_or("error: truncated protobuf field")?;
+                fields.push((key >> 3, ProtoValue::Bytes(&data[pos..end])));
+                len
+            }
+            1 => 8,
+            5 => 4,
+            _ => return Err("error: unsupported protobuf wire type".into()),
+        };
+        pos += skip;
+    }
+    if pos > data.len() {
+        return Err("error: truncated protobuf field".into());
+    }
+    Ok(fields)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_varint_field(out: &mut Vec<u8>, field: u64, value: u64) {
+    write_varint(out, field << 3);
+    write_varint(out, value);
+}
+
+fn write_bytes_field(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    write_varint(out, (field << 3) | 2);
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn decode_migration_account(data: &[u8]) -> Result<OtpAuthUri, String> {
+    let mut secret: &[u8] = &[];
+    let mut name = String::new();
+    let mut issuer = String::new();
+    let (mut algorithm, mut digits, mut kind, mut counter) = (0, 0, 0, 0);
+    for (field, value) in read_fields(data)? {
+        match (field, value) {
+            (1, ProtoValue::Bytes(b)) => secret = b,
+            (2, ProtoValue::Bytes(b)) => {
+                name = String::from_utf8(b.to_vec()).map_err(|_| "error: invalid account name")?
+            }
+            (3, ProtoValue::Bytes(b)) => {
+                issuer = String::from_utf8(b.to_vec()).map_err(|_| "error: invalid issuer")?
+            }
+            (4, ProtoValue::Varint(v)) => algorithm = v,
+            (5, ProtoValue::Varint(v)) => digits = v,
+            (6, ProtoValue::Varint(v)) => kind = v,
+            (7, ProtoValue::Varint(v)) => counter = v,
+            _ => {}
+        }
+    }
+
+    let algorithm = match algorithm {
+        0 | 1 => OtpAlgorithm::Sha1,
+        2 => OtpAlgorithm::Sha256,
+        3 => OtpAlgorithm::Sha512,
+        _ => {
+            return Err(format!(
+                "error: unsupported migration algorithm: {}",
+                algorithm
+            ));
+        }
+    };
+    let digits = match digits {
+        0 | 1 => 6,
+        2 => 8,
+        _ => return Err(format!("error: unsupported migration digits: {}", digits)),
+    };
+    let kind = match kind {
+        1 => OtpKind::Hotp,
+        _ => OtpKind::Totp,
+    };
+
+    // names are often exported as "Issuer:account"
+    let account = match name.split_once(':') {
+        Some((prefix, rest)) if issuer.is_empty() || prefix == issuer => {
+            issuer = prefix.to_string();
+            rest.trim_start().to_string()
+        }
+        _ => name,
+    };
+
+    let params = TotpParams::try_new(algorithm, digits, 30, 0)?;
+    OtpAuthUri::try_new(
+        kind,
+        Some(issuer),
+        &account,
+        &BASE32_NOPAD.encode(secret),
+        params,
+        counter,
+    )
+}
+
+fn encode_migration_account(account: &OtpAuthUri) -> Result<Vec<u8>, String> {
+    let digits = match account.params.digits() {
+        6 => 1,
+        8 => 2,
+        d => {
+            return Err(format!(
+                "error: migration payload cannot store {} digits",
+                d
+            ));
+        }
+    };
+    if account.kind == OtpKind::Totp && account.params.period() != 30 {
+        return Err("error: migration payload only supports a 30 second period".into());
+    }
+    let algorithm = match account.params.algorithm() {
+        OtpAlgorithm::Sha1 => 1,
+        OtpAlgorithm::Sha256 => 2,
+        OtpAlgorithm::Sha512 => 3,
+    };
+
+    let mut out = Vec::new();
+    write_bytes_field(&mut out, 1, &decode_secret(&account.secret)?);
+    write_bytes_field(&mut out, 2, account.account.as_bytes());
+    if let Some(issuer) = &account.issuer {
+        write_bytes_field(&mut out, 3, issuer.as_bytes());
+    }
+    write_varint_field(&mut out, 4, algorithm);
+    write_varint_field(&mut out, 5, digits);
+    match account.kind {
+        OtpKind::Hotp => {
+            write_varint_field(&mut out, 6, 1);
+            write_varint_field(&mut out, 7, account.counter);
+        }
+        OtpKind::Totp => write_varint_field(&mut out, 6, 2),
+    }
+    Ok(out)
+}
+
+#[wasm_bindgen]
+/// `OtpMigrationBatch` accounts decoded from one otpauth-migration:// URI
+pub struct OtpMigrationBatch {
+    accounts: Vec<OtpAuthUri>,
+    version: i32,
+    batch_size: i32,
+    batch_index: i32,
+    batch_id: i32,
+}
+
+#[wasm_bindgen]
+impl OtpMigrationBatch {
+    /// `accounts` decoded OTP accounts
+    pub fn accounts(&self) -> Vec<OtpAuthUri> {
+        self.accounts.clone()
+    }
+
+    /// `version` payload version
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// `batch_size` total number of URIs in the export
+    pub fn batch_size(&self) -> i32 {
+        self.batch_size
+    }
+
+    /// `batch_index` position of this URI in the export (0-based)
+    pub fn batch_index(&self) -> i32 {
+        self.batch_index
+    }
+
+    /// `batch_id` identifier shared by all URIs of one export
+    pub fn batch_id(&self) -> i32 {
+        self.batch_id
+    }
+}
+
+#[wasm_bindgen]
+/// `OtpMigration` Google Authenticator otpauth-migration:// import / export
+pub struct OtpMigration {}
+
+#[wasm_bindgen]
+impl OtpMigration {
+    /// `try_decode` decode an otpauth-migration:// URI
+    pub(crate) fn try_decode(uri: &str) -> Result<OtpMigrationBatch, String> {
+        let query = uri
+            .strip_prefix(MIGRATION_PREFIX)
+            .ok_or("error: uri must start with otpauth-migration://offline")?;
+        let data = query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("data="))
+            .ok_or("error: data is required")?;
+        let data = percent_decode(data, false)?;
+        let payload = BASE64
+            .decode(data.as_bytes())
+            .or_else(|_| BASE64_NOPAD.decode(data.as_bytes()))
+            .map_err(|_| "error: invalid base64 data")?;
+
+        let mut batch = OtpMigrationBatch {
+            accounts: Vec::new(),
+            version: 0,
+            batch_size: 1,
+            batch_index: 0,
+            batch_id: 0,
+        };
+        for (field, value) in read_fields(&payload)? {
+            match (field, value) {
+                (1, ProtoValue::Bytes(b)) => batch.accounts.push(decode_migration_account(b)?),
+                (2, ProtoValue::Varint(v)) => batch.version = v as i32,
+                (3, ProtoValue::Varint(v)) => batch.batch_size = v as i32,
+                (4, ProtoValue::Varint(v)) => batch.batch_index = v as i32,
+                (5, ProtoValue::Varint(v)) => batch.batch_id = v as i32,
+                _ => {}
+            }
+        }
+        Ok(batch)
+    }
+
+    /// `try_encode` encode accounts into migration URIs
+    pub(crate) fn try_encode(
+        accounts: &[OtpAuthUri],
+        per_batch: usize,
+    ) -> Result<Vec<String>, String> {
+        if accounts.is_empty() {
+            return Err("error: at least one account is required".into());
+        }
+        if per_batch == 0 {
+            return Err("error: per_batch must be greater than 0".into());
+        }
+
+        let batch_id = i32::from_be_bytes(
+            SafeRandom::gen_bytes(4)
+                .raw()
+                .try_into()
+                .expect("4 random bytes"),
+        ) & i32::MAX;
+        let chunks: Vec<&[OtpAuthUri]> = accounts.chunks(per_batch).collect();
+        let mut uris = Vec::with_capacity(chunks.len());
+        for (index, chunk) in chunks.iter().enumerate() {
+            let mut payload = Vec::new();
+            for account in chunk.iter() {
+                write_bytes_field(&mut payload, 1, &encode_migration_account(account)?);
+            }
+            write_varint_field(&mut payload, 2, 1);
+            write_varint_field(&mut payload, 3, chunks.len() as u64);
+            write_varint_field(&mut payload, 4, index as u64);
+            write_varint_field(&mut payload, 5, batch_id as u64);
+            uris.push(format!(
+                "{}data={}",
+                MIGRATION_PREFIX,
+                percent_encode(&BASE64.encode(&payload))
+            ));
+        }
+        Ok(uris)
+    }
+
+    /// `decode` decode an otpauth-migration://offline?data=... URI
+    pub fn decode(uri: &str) -> Result<OtpMigrationBatch, JsValue> {
+        Self::try_decode(uri).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `decode_luma` decode migration QR codes from grayscale image data
+    ///
+    /// ### Arguments
+    /// * `gray_bytes` - Grayscale pixel data (0-255, where 0 is black and 255 is white)
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    ///
+    /// ### Returns
+    /// Vec<OtpMigrationBatch>, one per migration QR code found
+    pub fn decode_luma(
+        gray_bytes: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<OtpMigrationBatch>, JsValue> {
+        QRCode::decode_luma(gray_bytes, width, height)?
+            .iter()
+            .filter(|content| content.starts_with(MIGRATION_PREFIX))
+            .map(|content| Self::decode(content))
+            .collect()
+    }
+
+    /// `encode` encode accounts into migration URIs
+    ///
+    /// ### Arguments
+    /// * `accounts` - OTP accounts to export
+    /// * `per_batch` - Maximum number of accounts per URI / QR code
+    ///
+    /// ### Returns
+    /// Vec<String> of otpauth-migration:// URIs sharing one batch id
+    pub fn encode(accounts: Vec<OtpAuthUri>, per_batch: usize) -> Result<Vec<String>, JsValue> {
+        Self::try_encode(&accounts, per_batch).map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[test]
+fn otpauth_uri_parse_test() {
+    let uri = "otpauth://totp/ACME%20Co:john.doe@email.com?secret=hxdm%20vjec%20jjws%20rb3h%20wizr%204ifu%20gftm%20xboz&issuer=ACME+Co&algorithm=SHA256&digits=8&period=60";
+    let parsed = OtpAuthUri::parse(uri).unwrap();
+    assert_eq!(OtpKind::Totp, parsed.kind());
+    assert_eq!(Some("ACME Co".to_string()), parsed.issuer());
+    assert_eq!("john.doe@email.com", parsed.account());
+    assert_eq!("HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ", parsed.secret());
+    assert_eq!(OtpAlgorithm::Sha256, parsed.algorithm());
+    assert_eq!(8, parsed.digits());
+    assert_eq!(60, parsed.period());
+
+    let serialized = parsed.to_uri();
+    println!("otpauth uri: {}", serialized);
+    assert_eq!(
+        "otpauth://totp/ACME%20Co:john.doe%40email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60",
+        serialized
+    );
+    let reparsed = OtpAuthUri::parse(&serialized).unwrap();
+    assert_eq!(parsed.to_uri(), reparsed.to_uri());
+
+    // defaults and label-only issuer
+    let parsed =
+        OtpAuthUri::parse("otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP")
+            .unwrap();
+    assert_eq!(Some("Example".to_string()), parsed.issuer());
+    assert_eq!(OtpAlgorithm::Sha1, parsed.algorithm());
+    assert_eq!(6, parsed.digits());
+    assert_eq!(30, parsed.period());
+}
+
+#[test]
+fn otpauth_uri_invalid_test() {
+    let invalid = [
+        "https://totp/Example:alice?secret=JBSWY3DPEHPK3PXP",
+        "otpauth://totp/Example:alice",
+        "otpauth://totp/Example:alice?issuer=Example",
+        "otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PX1",
+        "otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP&issuer=Other",
+        "otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP&digits=5",
+        "otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP&algorithm=MD5",
+        "otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP&secret=JBSWY3DPEHPK3PXP",
+        "otpauth://totp/Example%3?secret=JBSWY3DPEHPK3PXP",
+        "otpauth://hotp/Example:alice?secret=JBSWY3DPEHPK3PXP",
+        "otpauth://xotp/Example:alice?secret=JBSWY3DPEHPK3PXP",
+    ];
+    for uri in invalid {
+        let result = OtpAuthUri::try_parse(uri);
+        println!("{} -> {:?}", uri, result.as_ref().err());
+        assert!(result.is_err());
+    }
+}
+
+#[test]
+fn otpauth_uri_generate_test() {
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    let params = TotpParams::new(OtpAlgorithm::Sha1, 8, 30, 0).unwrap();
+    let totp = OtpAuthUri::new_totp(Some("RFC".to_string()), "6238", secret, &params).unwrap();
+    assert_eq!("94287082", totp.generate(59));
+
+    let hotp = OtpAuthUri::new_hotp(None, "4226", secret, OtpAlgorithm::Sha1, 6, 1).unwrap();
+    println!("hotp uri: {}", hotp.to_uri());
+    let parsed = OtpAuthUri::parse(&hotp.to_uri()).unwrap();
+    assert_eq!(OtpKind::Hotp, parsed.kind());
+    assert_eq!(None, parsed.issuer());
+    assert_eq!("287082", parsed.generate(0));
+}
+
+#[test]
+fn otp_migration_decode_test() {
+    let uri = "otpauth-migration://offline?data=CjUKCkhlbGxvId6tvu8SGEV4YW1wbGU6YWxpY2VAZ29vZ2xlLmNvbRoHRXhhbXBsZSABKAEwAgokChQxMjM0NTY3ODkwMTIzNDU2Nzg5MBIDYm9iIAIoAjABOKwCEAEYASAAKJWa7zo%3D";
+    let batch = OtpMigration::decode(uri).unwrap();
+    assert_eq!(1, batch.version());
+    assert_eq!(1, batch.batch_size());
+    assert_eq!(0, batch.batch_index());
+    assert_eq!(123456789, batch.batch_id());
+
+    let accounts = batch.accounts();
+    assert_eq!(2, accounts.len());
+    for account in &accounts {
+        println!("migration account: {}", account.to_uri());
+    }
+
+    assert_eq!(OtpKind::Totp, accounts[0].kind());
+    assert_eq!(Some("Example".to_string()), accounts[0].issuer());
+    assert_eq!("alice@google.com", accounts[0].account());
+    assert_eq!("JBSWY3DPEHPK3PXP", accounts[0].secret());
+    assert_eq!(OtpAlgorithm::Sha1, accounts[0].algorithm());
+    assert_eq!(6, accounts[0].digits());
+
+    assert_eq!(OtpKind::Hotp, accounts[1].kind());
+    assert_eq!(None, accounts[1].issuer());
+    assert_eq!("bob", accounts[1].account());
+    assert_eq!("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ", accounts[1].secret());
+    assert_eq!(OtpAlgorithm::Sha256, accounts[1].algorithm());
+    assert_eq!(8, accounts[1].digits());
+    assert_eq!(300, accounts[1].counter());
+}
+
+#[test]
+fn otp_migration_roundtrip_test() {
+    let accounts: Vec<OtpAuthUri> = (0..5)
+        .map(|i| {
+            OtpAuthUri::new_totp(
+                Some("Example".to_string()),
+                &format!("user{}@example.com", i),
+                "JBSWY3DPEHPK3PXP",
+                &TotpParams::standard(),
+            )
+            .unwrap()
+        })
+        .collect();
+
+    let uris = OtpMigration::encode(accounts.clone(), 2).unwrap();
+    println!("migration uris: {:?}", uris);
+    assert_eq!(3, uris.len());
+
+    let mut decoded = Vec::new();
+    for (index, uri) in uris.iter().enumerate() {
+        let batch = OtpMigration::decode(uri).unwrap();
+        assert_eq!(3, batch.batch_size());
+        assert_eq!(index as i32, batch.batch_index());
+        decoded.extend(batch.accounts());
+    }
+    let original: Vec<String> = accounts.iter().map(|a| a.to_uri()).collect();
+    let restored: Vec<String> = decoded.iter().map(|a| a.to_uri()).collect();
+    assert_eq!(original, restored);
+
+    // periods other than 30 seconds cannot be exported
+    let custom = TotpParams::new(OtpAlgorithm::Sha1, 6, 60, 0).unwrap();
+    let account = OtpAuthUri::new_totp(None, "alice", "JBSWY3DPEHPK3PXP", &custom).unwrap();
+    assert!(OtpMigration::try_encode(&[account], 1).is_err());
+}
+
+#[test]
+fn otp_migration_qrcode_test() {
+    let account = OtpAuthUri::new_totp(
+        Some("Example".to_string()),
+        "alice@google.com",
+        "JBSWY3DPEHPK3PXP",
+        &TotpParams::standard(),
+    )
+    .unwrap();
+    let uris = OtpMigration::encode(vec![account.clone()], 10).unwrap();
+
+    let qr_raw = QRCode::raw_default_unpacked(&uris[0]).unwrap();
+    let width = qr_raw[0] as u32;
+    let gray_bytes: Vec<u8> = qr_raw[2..]
+        .iter()
+        .map(|&m| if m == 1 { 0 } else { 255 })
+        .collect();
+
+    let batches = OtpMigration::decode_luma(&gray_bytes, width, width).unwrap();
+    assert_eq!(1, batches.len());
+    assert_eq!(account.to_uri(), batches[0].accounts()[0].to_uri());
+}
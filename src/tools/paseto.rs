@@ -0,0 +1,442 @@
+use crate::tools::hash::Hash;
+use crate::tools::random::SafeRandom;
+use blake2::Blake2bMac;
+use chacha20::XChaCha20;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use data_encoding::BASE64URL_NOPAD;
+use digest::consts::{U32, U56};
+use ed25519_zebra::VerificationKey;
+use subtle::ConstantTimeEq;
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+const LOCAL_HEADER: &str = "v4.local.";
+const PUBLIC_HEADER: &str = "v4.public.";
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 32;
+const MAC_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+const ENCRYPTION_KEY_INFO: &[u8] = b"paseto-encryption-key";
+const AUTH_KEY_INFO: &[u8] = b"paseto-auth-key-for-aead";
+
+/// `pae` pre-authentication encoding: piece count and lengths as LE64, then the pieces
+fn pae(pieces: &[&[u8]]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(pieces.len() as u64).to_le_bytes());
+    for piece in pieces {
+        out.extend_from_slice(&(piece.len() as u64).to_le_bytes());
+        out.extend_from_slice(piece);
+    }
+    out
+}
+
+/// `split_token` check the header and return the decoded body and footer
+fn split_token(token: &str, header: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let rest = token
+        .strip_prefix(header)
+        .ok_or_else(|| format!("error: token must start with {}", header))?;
+    let (body, footer) = match rest.split_once('.') {
+        Some((body, footer)) => (body, footer),
+        None => (rest, ""),
+    };
+    let decode = |part: &str| {
+        BASE64URL_NOPAD
+            .decode(part.as_bytes())
+            .map_err(|_| "error: invalid base64url in token".to_string())
+    };
+    Ok((decode(body)?, decode(footer)?))
+}
+
+/// `check_footer` constant-time comparison with the footer the caller expects
+fn check_footer(actual: &[u8], expected: &str) -> Result<(), String> {
+    if bool::from(actual.ct_eq(expected.as_bytes())) {
+        Ok(())
+    } else {
+        Err("error: token footer mismatch".into())
+    }
+}
+
+/// `join_token` header || base64url(body) [ || "." || base64url(footer) ]
+fn join_token(header: &str, body: &[u8], footer: &str) -> String {
+    let mut token = format!("{}{}", header, BASE64URL_NOPAD.encode(body));
+    if !footer.is_empty() {
+        token.push('.');
+        token.push_str(&BASE64URL_NOPAD.encode(footer.as_bytes()));
+    }
+    token
+}
+
+/// `local_keys` split the local key into an XChaCha20 key, XChaCha20 nonce and MAC key
+fn local_keys(key: &[u8], nonce: &[u8]) -> (Zeroizing<Vec<u8>>, Zeroizing<Vec<u8>>) {
+    let encryption = Zeroizing::new(Hash::compute_hmac::<Blake2bMac<U56>>(
+        key,
+        &[ENCRYPTION_KEY_INFO, nonce].concat(),
+    ));
+    let auth = Zeroizing::new(Hash::compute_hmac::<Blake2bMac<U32>>(
+        key,
+        &[AUTH_KEY_INFO, nonce].concat(),
+    ));
+    (encryption, auth)
+}
+
+fn xchacha20(encryption: &[u8], data: &mut [u8]) {
+    let mut cipher = XChaCha20::new(encryption[..32].into(), encryption[32..].into());
+    cipher.apply_keystream(data);
+}
+
+/// `secret_seed` accept a 32-byte seed or a 64-byte seed || public key
+fn secret_seed(secret_key: &[u8]) -> Result<&[u8], String> {
+    match secret_key.len() {
+        32 => Ok(secret_key),
+        64 => {
+            let public_key = Paseto::try_public_key(&secret_key[..32])?;
+            if public_key != secret_key[32..] {
+                return Err("error: secret key does not match its public key".into());
+            }
+            Ok(&secret_key[..32])
+        }
+        _ => Err("error: Ed25519 secret key must be 32 or 64 bytes".into()),
+    }
+}
+
+#[wasm_bindgen]
+/// `Paseto` PASETO v4 tokens: `v4.local` (XChaCha20 + BLAKE2b-MAC) and `v4.public` (Ed25519)
+pub struct Paseto {}
+
+#[wasm_bindgen]
+impl Paseto {
+    /// `try_encrypt_local_with_nonce` v4.local with a caller-chosen nonce
+    pub(crate) fn try_encrypt_local_with_nonce(
+        key: &[u8],
+        payload: &str,
+        footer: &str,
+        implicit: &str,
+        nonce: &[u8],
+    ) -> Result<String, String> {
+        if key.len() != KEY_LEN {
+            return Err("error: v4.local key must be 32 bytes".into());
+        }
+        let (encryption, auth) = local_keys(key, nonce);
+        let mut ciphertext = payload.as_bytes().to_vec();
+        xchacha20(&encryption, &mut ciphertext);
+        let pre_auth = pae(&[
+            LOCAL_HEADER.as_bytes(),
+            nonce,
+            &ciphertext,
+            footer.as_bytes(),
+            implicit.as_bytes(),
+        ]);
+        let tag = Hash::compute_hmac::<Blake2bMac<U32>>(&auth, &pre_auth);
+        let body = [nonce, &ciphertext, &tag].concat();
+        Ok(join_token(LOCAL_HEADER, &body, footer))
+    }
+
+    /// `try_decrypt_local` verify and decrypt a v4.local token
+    pub(crate) fn try_decrypt_local(
+        key: &[u8],
+        token: &str,
+        footer: &str,
+        implicit: &str,
+    ) -> Result<String, String> {
+        if key.len() != KEY_LEN {
+            return Err("error: v4.local key must be 32 bytes".into());
+        }
+        let (body, actual_footer) = split_token(token, LOCAL_HEADER)?;
+        check_footer(&actual_footer, footer)?;
+        if body.len() < NONCE_LEN + MAC_LEN {
+            return Err("error: token is too short".into());
+        }
+        let (nonce, rest) = body.split_at(NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - MAC_LEN);
+
+        let (encryption, auth) = local_keys(key, nonce);
+        let pre_auth = pae(&[
+            LOCAL_HEADER.as_bytes(),
+            nonce,
+            ciphertext,
+            &actual_footer,
+            implicit.as_bytes(),
+        ]);
+        let expected = Hash::compute_hmac::<Blake2bMac<U32>>(&auth, &pre_auth);
+        if !bool::from(expected.ct_eq(tag)) {
+            return Err("error: invalid token authentication tag".into());
+        }
+        let mut payload = ciphertext.to_vec();
+        xchacha20(&encryption, &mut payload);
+        String::from_utf8(payload).map_err(|_| "error: token payload is not UTF-8".into())
+    }
+
+    /// `try_sign_public` create a v4.public token
+    pub(crate) fn try_sign_public(
+        secret_key: &[u8],
+        payload: &str,
+        footer: &str,
+        implicit: &str,
+    ) -> Result<String, String> {
+        let signing_key = Hash::ed25519_signing_key(secret_seed(secret_key)?)?;
+        let pre_auth = pae(&[
+            PUBLIC_HEADER.as_bytes(),
+            payload.as_bytes(),
+            footer.as_bytes(),
+            implicit.as_bytes(),
+        ]);
+        let signature = signing_key.sign(&pre_auth).to_bytes();
+        let body = [payload.as_bytes(), &signature].concat();
+        Ok(join_token(PUBLIC_HEADER, &body, footer))
+    }
+
+    /// `try_verify_public` verify a v4.public token and return its payload
+    pub(crate) fn try_verify_public(
+        public_key: &[u8],
+        token: &str,
+        footer: &str,
+        implicit: &str,
+    ) -> Result<String, String> {
+        if public_key.len() != 32 {
+            return Err("error: Ed25519 public key must be 32 bytes".into());
+        }
+        let (body, actual_footer) = split_token(token, PUBLIC_HEADER)?;
+        check_footer(&actual_footer, footer)?;
+        if body.len() < SIGNATURE_LEN {
+            return Err("error: token is too short".into());
+        }
+        let (payload, signature) = body.split_at(body.len() - SIGNATURE_LEN);
+        let pre_auth = pae(&[
+            PUBLIC_HEADER.as_bytes(),
+            payload,
+            &actual_footer,
+            implicit.as_bytes(),
+        ]);
+        if !Hash::ed25519_verify_bytes(public_key, &pre_auth, signature) {
+            return Err("error: invalid token signature".into());
+        }
+        String::from_utf8(payload.to_vec()).map_err(|_| "error: token payload is not UTF-8".into())
+    }
+
+    /// `try_public_key` Ed25519 public key of a 32-byte seed
+    pub(crate) fn try_public_key(seed: &[u8]) -> Result<Vec<u8>, String> {
+        let signing_key = Hash::ed25519_signing_key(seed)?;
+        Ok(<[u8; 32]>::from(VerificationKey::from(&signing_key)).to_vec())
+    }
+
+    /// `generate_local_key` random 32-byte v4.local key
+    pub fn generate_local_key() -> Vec<u8> {
+        SafeRandom::gen_bytes(KEY_LEN).raw()
+    }
+
+    /// `generate_secret_key` random Ed25519 secret key as 64 bytes (seed || public key)
+    pub fn generate_secret_key() -> Vec<u8> {
+        let seed = Zeroizing::new(SafeRandom::gen_bytes(32).raw());
+        let public_key = Self::try_public_key(&seed).expect("32-byte seed");
+        [seed.as_slice(), &public_key].concat()
+    }
+
+    /// `public_key` Ed25519 public key for a 32- or 64-byte secret key
+    pub fn public_key(secret_key: &[u8]) -> Result<Vec<u8>, JsValue> {
+        secret_seed(secret_key)
+            .and_then(Self::try_public_key)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `encrypt_local` create a v4.local token
+    ///
+    /// ### Arguments
+    /// * `key` - 32-byte symmetric key
+    /// * `payload` - Claims, usually JSON
+    /// * `footer` - Unencrypted but authenticated footer, `""` for none
+    /// * `implicit` - Implicit assertion, authenticated but not stored in the token
+    pub fn encrypt_local(
+        key: &[u8],
+        payload: &str,
+        footer: &str,
+        implicit: &str,
+    ) -> Result<String, JsValue> {
+        let nonce = SafeRandom::gen_bytes(NONCE_LEN).raw();
+        Self::try_encrypt_local_with_nonce(key, payload, footer, implicit, &nonce)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `decrypt_local` verify and decrypt a v4.local token; `footer` and `implicit` must match
+    pub fn decrypt_local(
+        key: &[u8],
+        token: &str,
+        footer: &str,
+        implicit: &str,
+    ) -> Result<String, JsValue> {
+        Self::try_decrypt_local(key, token, footer, implicit).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `sign_public` create a v4.public token
+    ///
+    /// ### Arguments
+    /// * `secret_key` - Ed25519 secret key, 32-byte seed or 64-byte seed || public key
+    /// * `payload` - Claims, usually JSON
+    /// * `footer` - Authenticated footer, `""` for none
+    /// * `implicit` - Implicit assertion, authenticated but not stored in the token
+    pub fn sign_public(
+        secret_key: &[u8],
+        payload: &str,
+        footer: &str,
+        implicit: &str,
+    ) -> Result<String, JsValue> {
+        Self::try_sign_public(secret_key, payload, footer, implicit)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `verify_public` verify a v4.public token and return its payload; `footer` and `implicit` must match
+    pub fn verify_public(
+        public_key: &[u8],
+        token: &str,
+        footer: &str,
+        implicit: &str,
+    ) -> Result<String, JsValue> {
+        Self::try_verify_public(public_key, token, footer, implicit)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `footer` unverified footer of a token, e.g. to look up the key by `kid`
+    pub fn footer(token: &str) -> Result<String, JsValue> {
+        let footer = token.splitn(4, '.').nth(3).unwrap_or_default();
+        BASE64URL_NOPAD
+            .decode(footer.as_bytes())
+            .ok()
+            .and_then(|footer| String::from_utf8(footer).ok())
+            .ok_or_else(|| JsValue::from_str("error: invalid token footer"))
+    }
+}
+
+#[cfg(test)]
+fn from_hex(hex: &str) -> Vec<u8> {
+    data_encoding::HEXLOWER.decode(hex.as_bytes()).unwrap()
+}
+
+#[cfg(test)]
+const KID_FOOTER: &str = r#"{"kid":"zVhMiPBP9fRf2snEcT7gFTioeA9COcNy9DfgL1W60haN"}"#;
+
+#[test]
+fn paseto_v4_local_test() {
+    // official PASETO v4 test vectors 4-E-1, 4-E-3, 4-E-5 and 4-E-7
+    let key = from_hex("707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f");
+    let payload = r#"{"data":"this is a secret message","exp":"2022-01-01T00:00:00+00:00"}"#;
+    let nonce = from_hex("df654812bac492663825520ba2f6e67cf5ca5bdc13d4e7507a98cc4c2fcc3ad8");
+    let implicit = r#"{"test-vector":"4-E-7"}"#;
+    let vectors = [
+        (
+            vec![0u8; 32],
+            "",
+            "",
+            "v4.local.AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAr68PS4AXe7If_ZgesdkUMvSwscFlAl1pk5HC0e8kApeaqMfGo_7OpBnwJOAbY9V7WU6abu74MmcUE8YWAiaArVI8XJ5hOb_4v9RmDkneN0S92dx0OW4pgy7omxgf3S8c3LlQg",
+        ),
+        (
+            nonce.clone(),
+            "",
+            "",
+            "v4.local.32VIErrEkmY4JVILovbmfPXKW9wT1OdQepjMTC_MOtjA4kiqw7_tcaOM5GNEcnTxl60WkwMsYXw6FSNb_UdJPXjpzm0KW9ojM5f4O2mRvE2IcweP-PRdoHjd5-RHCiExR1IK6t6-tyebyWG6Ov7kKvBdkrrAJ837lKP3iDag2hzUPHuMKA",
+        ),
+        (
+            nonce.clone(),
+            KID_FOOTER,
+            "",
+            "v4.local.32VIErrEkmY4JVILovbmfPXKW9wT1OdQepjMTC_MOtjA4kiqw7_tcaOM5GNEcnTxl60WkwMsYXw6FSNb_UdJPXjpzm0KW9ojM5f4O2mRvE2IcweP-PRdoHjd5-RHCiExR1IK6t4x-RMNXtQNbz7FvFZ_G-lFpk5RG3EOrwDL6CgDqcerSQ.eyJraWQiOiJ6VmhNaVBCUDlmUmYyc25FY1Q3Z0ZUaW9lQTlDT2NOeTlEZmdMMVc2MGhhTiJ9",
+        ),
+        (
+            nonce,
+            KID_FOOTER,
+            implicit,
+            "v4.local.32VIErrEkmY4JVILovbmfPXKW9wT1OdQepjMTC_MOtjA4kiqw7_tcaOM5GNEcnTxl60WkwMsYXw6FSNb_UdJPXjpzm0KW9ojM5f4O2mRvE2IcweP-PRdoHjd5-RHCiExR1IK6t40KCCWLA7GYL9KFHzKlwY9_RnIfRrMQpueydLEAZGGcA.eyJraWQiOiJ6VmhNaVBCUDlmUmYyc25FY1Q3Z0ZUaW9lQTlDT2NOeTlEZmdMMVc2MGhhTiJ9",
+        ),
+    ];
+    for (nonce, footer, implicit, token) in vectors.clone() {
+        assert_eq!(
+            token,
+            Paseto::try_encrypt_local_with_nonce(&key, payload, footer, implicit, &nonce).unwrap()
+        );
+        assert_eq!(
+            payload,
+            Paseto::try_decrypt_local(&key, token, footer, implicit).unwrap()
+        );
+        assert_eq!(footer, Paseto::footer(token).unwrap());
+    }
+
+    let token = vectors[3].3;
+    assert!(Paseto::try_decrypt_local(&key, token, KID_FOOTER, "").is_err());
+    assert!(Paseto::try_decrypt_local(&key, token, "", implicit).is_err());
+    assert!(Paseto::try_decrypt_local(&[0u8; 32], token, KID_FOOTER, implicit).is_err());
+    let tampered = token.replacen("32VI", "32VJ", 1);
+    assert!(Paseto::try_decrypt_local(&key, &tampered, KID_FOOTER, implicit).is_err());
+    let public = token.replacen("v4.local.", "v4.public.", 1);
+    assert!(Paseto::try_decrypt_local(&key, &public, KID_FOOTER, implicit).is_err());
+    assert!(Paseto::try_decrypt_local(&key[..16], token, KID_FOOTER, implicit).is_err());
+
+    let key = Paseto::generate_local_key();
+    let token = Paseto::encrypt_local(&key, payload, "", "").unwrap();
+    println!("v4.local: {}", token);
+    assert_eq!(
+        payload,
+        Paseto::try_decrypt_local(&key, &token, "", "").unwrap()
+    );
+}
+
+#[test]
+fn paseto_v4_public_test() {
+    // official PASETO v4 test vectors 4-S-1, 4-S-2 and 4-S-3
+    let secret_key = from_hex(
+        "b4cbfb43df4ce210727d953e4a713307fa19bb7d9f85041438d9e11b942a37741eb9dbbbbc047c03fd70604e0071f0987e16b28b757225c11f00415d0e20b1a2",
+    );
+    let public_key = from_hex("1eb9dbbbbc047c03fd70604e0071f0987e16b28b757225c11f00415d0e20b1a2");
+    let payload = r#"{"data":"this is a signed message","exp":"2022-01-01T00:00:00+00:00"}"#;
+    let implicit = r#"{"test-vector":"4-S-3"}"#;
+    let vectors = [
+        (
+            "",
+            "",
+            "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9bg_XBBzds8lTZShVlwwKSgeKpLT3yukTw6JUz3W4h_ExsQV-P0V54zemZDcAxFaSeef1QlXEFtkqxT1ciiQEDA",
+        ),
+        (
+            KID_FOOTER,
+            "",
+            "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9v3Jt8mx_TdM2ceTGoqwrh4yDFn0XsHvvV_D0DtwQxVrJEBMl0F2caAdgnpKlt4p7xBnx1HcO-SPo8FPp214HDw.eyJraWQiOiJ6VmhNaVBCUDlmUmYyc25FY1Q3Z0ZUaW9lQTlDT2NOeTlEZmdMMVc2MGhhTiJ9",
+        ),
+        (
+            KID_FOOTER,
+            implicit,
+            "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9NPWciuD3d0o5eXJXG5pJy-DiVEoyPYWs1YSTwWHNJq6DZD3je5gf-0M4JR9ipdUSJbIovzmBECeaWmaqcaP0DQ.eyJraWQiOiJ6VmhNaVBCUDlmUmYyc25FY1Q3Z0ZUaW9lQTlDT2NOeTlEZmdMMVc2MGhhTiJ9",
+        ),
+    ];
+    assert_eq!(
+        public_key,
+        Paseto::try_public_key(&secret_key[..32]).unwrap()
+    );
+    for (footer, implicit, token) in vectors {
+        assert_eq!(
+            token,
+            Paseto::try_sign_public(&secret_key, payload, footer, implicit).unwrap()
+        );
+        assert_eq!(
+            token,
+            Paseto::try_sign_public(&secret_key[..32], payload, footer, implicit).unwrap()
+        );
+        assert_eq!(
+            payload,
+            Paseto::try_verify_public(&public_key, token, footer, implicit).unwrap()
+        );
+    }
+
+    let token = vectors[2].2;
+    assert!(Paseto::try_verify_public(&public_key, token, KID_FOOTER, "").is_err());
+    assert!(Paseto::try_verify_public(&public_key, token, "", implicit).is_err());
+    let tampered = token.replacen("eyJkYXRh", "eyJkYXRi", 1);
+    assert!(Paseto::try_verify_public(&public_key, &tampered, KID_FOOTER, implicit).is_err());
+    let mut wrong_pair = secret_key.clone();
+    wrong_pair[63] ^= 1;
+    assert!(Paseto::try_sign_public(&wrong_pair, payload, "", "").is_err());
+
+    let secret_key = Paseto::generate_secret_key();
+    let public_key = Paseto::public_key(&secret_key).unwrap();
+    let token = Paseto::sign_public(&secret_key, payload, "", "").unwrap();
+    println!("v4.public: {}", token);
+    assert_eq!(
+        payload,
+        Paseto::try_verify_public(&public_key, &token, "", "").unwrap()
+    );
+}
@@ -1,3 +1,4 @@
+use qrcode::render::svg;
 use qrcode::{EcLevel, QrCode, Version};
 use rqrr;
 use wasm_bindgen::prelude::*;
@@ -162,6 +163,20 @@ impl QRCode {
             .build()
     }
 
+    /// `to_svg_string` Convert QrCode to an SVG document
+    ///
+    /// ### Arguments
+    /// * `code` - The QrCode object
+    ///
+    /// ### Returns
+    /// String containing a standalone SVG image with a quiet zone
+    fn to_svg_string(code: &QrCode) -> String {
+        code.render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .quiet_zone(true)
+            .build()
+    }
+
     /// `raw` Generate raw QR code data with custom version and error correction level
     ///
     /// ### Arguments
@@ -267,6 +282,31 @@ impl QRCode {
         Self::text_auto(data, QrEccLevel::Medium)
     }
 
+    /// `svg` Generate QR code as SVG with custom version and error correction level
+    ///
+    /// ### Arguments
+    /// * `data` - The data to encode
+    /// * `version` - QR code version (Micro1-Micro4 or Normal1-Normal40)
+    /// * `ec_level` - Error correction level (Low, Medium, Quartile, High)
+    ///
+    /// ### Returns
+    /// String containing a standalone SVG image
+    pub fn svg(data: &str, version: QrVersion, ec_level: QrEccLevel) -> Result<String, JsValue> {
+        let code = Self::generate_manual(data, version, ec_level)?;
+        Ok(Self::to_svg_string(&code))
+    }
+
+    /// `svg_auto` Generate QR code as SVG with error correction level
+    pub fn svg_auto(data: &str, ec_level: QrEccLevel) -> Result<String, JsValue> {
+        let code = Self::generate_auto_version(data, ec_level)?;
+        Ok(Self::to_svg_string(&code))
+    }
+
+    /// `svg_default` Generate QR code as SVG with default settings
+    pub fn svg_default(data: &str) -> Result<String, JsValue> {
+        Self::svg_auto(data, QrEccLevel::Medium)
+    }
+
     /// `decode_luma` Decode QR code from grayscale image data
     ///
     /// ### Arguments
@@ -336,6 +376,12 @@ fn qrcode_encode_test() {
     let qr_text = QRCode::text_default(data).unwrap();
     println!("QR text default: {}", qr_text);
     assert!(qr_text.len() > 2);
+
+    // svg
+    let qr_svg = QRCode::svg_default(data).unwrap();
+    println!("QR svg default: {}", &qr_svg[..80]);
+    assert!(qr_svg.starts_with("<?xml"));
+    assert!(qr_svg.contains("<svg"));
 }
 
 #[test]
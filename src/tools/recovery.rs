@@ -0,0 +1,336 @@
+use crate::tools::kdf::{DEFAULT_MAX_MEMORY_KIB, KdfParams};
+use crate::tools::random::SafeRandom;
+use data_encoding::{BASE64_NOPAD, HEXLOWER};
+use subtle::ConstantTimeEq;
+use wasm_bindgen::prelude::*;
+
+/// 29 symbols keeping one of each look-alike group (0/O, 1/I/L, 2/Z, 5/S, 8/B, U/V);
+/// `normalize` reads the dropped letters as the kept symbol
+const RECOVERY_ALPHABET: &[u8] = b"0123456789ACDEFGHJKMNPQRTUWXY";
+const LOOKALIKES: [(char, char); 7] = [
+    ('O', '0'),
+    ('I', '1'),
+    ('L', '1'),
+    ('Z', '2'),
+    ('S', '5'),
+    ('B', '8'),
+    ('V', 'U'),
+];
+// each code is hashed with Argon2id, so a huge set would stall the caller
+const MAX_COUNT: usize = 50;
+const MIN_SYMBOLS: usize = 8;
+const MAX_SYMBOLS: usize = 64;
+const SALT_LEN: usize = 16;
+const HASH_LEN: usize = 32;
+const DEFAULT_COUNT: usize = 10;
+const DEFAULT_GROUPS: usize = 2;
+const DEFAULT_GROUP_LEN: usize = 5;
+
+#[wasm_bindgen]
+/// `RecoveryCodeSet` freshly generated codes with their Argon2id hashes
+pub struct RecoveryCodeSet {
+    codes: Vec<String>,
+    hashes: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl RecoveryCodeSet {
+    /// `codes` plaintext codes to show the user once
+    pub fn codes(&self) -> Vec<String> {
+        self.codes.clone()
+    }
+
+    /// `hashes` `<kdf>$<params>$<salt>$<hash>` entries to store, in the same order as `codes`
+    pub fn hashes(&self) -> Vec<String> {
+        self.hashes.clone()
+    }
+}
+
+#[wasm_bindgen]
+/// `RecoveryVerification` outcome of checking a typed recovery code
+pub struct RecoveryVerification {
+    valid: bool,
+    index: Option<u32>,
+    remaining: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl RecoveryVerification {
+    /// `valid` whether the code matched a stored hash
+    pub fn valid(&self) -> bool {
+        self.valid
+    }
+
+    /// `index` position of the consumed hash
+    pub fn index(&self) -> Option<u32> {
+        self.index
+    }
+
+    /// `remaining` stored hashes with the consumed one removed
+    pub fn remaining(&self) -> Vec<String> {
+        self.remaining.clone()
+    }
+}
+
+#[wasm_bindgen]
+/// `RecoveryCodes` one-time MFA recovery codes
+pub struct RecoveryCodes {}
+
+#[wasm_bindgen]
+impl RecoveryCodes {
+    /// `try_generate` generate codes and hashes
+    pub(crate) fn try_generate(
+        count: usize,
+        groups: usize,
+        group_len: usize,
+    ) -> Result<RecoveryCodeSet, String> {
+        if count == 0 || count > MAX_COUNT {
+            return Err(format!("error: count must be between 1 and {}", MAX_COUNT));
+        }
+        if groups == 0 || group_len == 0 {
+            return Err("error: groups and group_len must be greater than 0".into());
+        }
+        let symbols = groups
+            .checked_mul(group_len)
+            .filter(|n| (MIN_SYMBOLS..=MAX_SYMBOLS).contains(n))
+            .ok_or_else(|| {
+                format!(
+                    "error: recovery codes need {} to {} symbols",
+                    MIN_SYMBOLS, MAX_SYMBOLS
+                )
+            })?;
+
+        let mut codes = Vec::with_capacity(count);
+        while codes.len() < count {
+            let code = Self::random_symbols(symbols)
+                .chunks(group_len)
+                .map(|chunk| chunk.iter().map(|&b| b as char).collect::<String>())
+                .collect::<Vec<String>>()
+                .join("-");
+            if !codes.contains(&code) {
+                codes.push(code);
+            }
+        }
+        let params = KdfParams::recommended();
+        let hashes = codes
+            .iter()
+            .map(|code| Self::try_hash_code(code, &params))
+            .collect::<Result<_, _>>()?;
+        Ok(RecoveryCodeSet { codes, hashes })
+    }
+
+    /// `random_symbols` uniform symbols from `RECOVERY_ALPHABET`, rejecting biased bytes
+    fn random_symbols(len: usize) -> Vec<u8> {
+        let limit = 256 - 256 % RECOVERY_ALPHABET.len();
+        let mut symbols = Vec::with_capacity(len);
+        while symbols.len() < len {
+            for b in SafeRandom::gen_bytes(len - symbols.len()).raw() {
+                if (b as usize) < limit {
+                    symbols.push(RECOVERY_ALPHABET[b as usize % RECOVERY_ALPHABET.len()]);
+                }
+            }
+        }
+        symbols
+    }
+
+    /// `matches` compare a normalized code with one stored `<kdf>$<params>$<salt>$<hash>` entry
+    fn matches(normalized: &str, stored: &str) -> bool {
+        let fields: Vec<&str> = stored.split('$').collect();
+        let [name, params, salt, hash] = fields.as_slice() else {
+            return false;
+        };
+        // stored entries may come from a leaked or edited table, so keep the KDF within budget
+        let Ok(params) = KdfParams::parse(name, params) else {
+            return false;
+        };
+        if params.check_memory(DEFAULT_MAX_MEMORY_KIB).is_err() {
+            return false;
+        }
+        let (Ok(salt), Ok(hash)) = (
+            BASE64_NOPAD.decode(salt.as_bytes()),
+            HEXLOWER.decode(hash.to_ascii_lowercase().as_bytes()),
+        ) else {
+            return false;
+        };
+        match params.derive(normalized.as_bytes(), &salt, HASH_LEN) {
+            Ok(derived) => derived.ct_eq(&hash).into(),
+            Err(_) => false,
+        }
+    }
+
+    /// `try_hash_code` hash a normalized code under a random salt with the given KDF
+    pub(crate) fn try_hash_code(code: &str, params: &KdfParams) -> Result<String, String> {
+        let salt = SafeRandom::gen_bytes(SALT_LEN).raw();
+        let hash = params.derive(Self::normalize(code).as_bytes(), &salt, HASH_LEN)?;
+        Ok(format!(
+            "{}${}${}",
+            params.encode(),
+            BASE64_NOPAD.encode(&salt),
+            HEXLOWER.encode(&hash)
+        ))
+    }
+
+    /// `normalize` uppercase, drop dashes and whitespace, and read look-alike letters as the kept symbol
+    pub fn normalize(code: &str) -> String {
+        code.chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| {
+                let c = c.to_ascii_uppercase();
+                LOOKALIKES
+                    .iter()
+                    .find(|(from, _)| *from == c)
+                    .map_or(c, |(_, to)| *to)
+            })
+            .collect()
+    }
+
+    /// `hash_code` Argon2id hash of a code for storage, as `argon2id$m=..,t=..,p=..$base64(salt)$hex(hash)`
+    ///
+    /// Codes carry only about 40-50 bits of entropy, so a fast hash would not survive a leaked table;
+    /// the KDF settings (`KdfParams::recommended()`) are recorded so they can be raised later.
+    pub fn hash_code(code: &str) -> Result<String, JsValue> {
+        Self::try_hash_code(code, &KdfParams::recommended()).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `generate` generate recovery codes
+    ///
+    /// ### Arguments
+    /// * `count` - Number of codes, at most 50
+    /// * `groups` - Groups per code, joined with `-`
+    /// * `group_len` - Symbols per group; 8 to 64 symbols in total
+    ///
+    /// ### Returns
+    /// `RecoveryCodeSet` with the plaintext codes and their Argon2id hashes
+    pub fn generate(
+        count: usize,
+        groups: usize,
+        group_len: usize,
+    ) -> Result<RecoveryCodeSet, JsValue> {
+        Self::try_generate(count, groups, group_len).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `generate_default` ten codes formatted as `XXXXX-XXXXX`
+    pub fn generate_default() -> RecoveryCodeSet {
+        Self::try_generate(DEFAULT_COUNT, DEFAULT_GROUPS, DEFAULT_GROUP_LEN)
+            .expect("default recovery code settings are valid")
+    }
+
+    /// `verify` check a typed code against stored hashes and consume the match
+    ///
+    /// ### Arguments
+    /// * `code` - Code as typed; case, dashes and spaces are ignored
+    /// * `hashes` - Stored `hash_code` entries; entries needing more than 256 MiB never match
+    ///
+    /// ### Returns
+    /// `RecoveryVerification` with the matched index and the hashes left to store
+    pub fn verify(code: &str, hashes: Vec<String>) -> RecoveryVerification {
+        let normalized = Self::normalize(code);
+        let mut index = None;
+        if !normalized.is_empty() {
+            // check every entry so timing does not reveal the position
+            for (i, stored) in hashes.iter().enumerate() {
+                if Self::matches(&normalized, stored) && index.is_none() {
+                    index = Some(i);
+                }
+            }
+        }
+
+        let mut remaining = hashes;
+        if let Some(i) = index {
+            remaining.remove(i);
+        }
+        RecoveryVerification {
+            valid: index.is_some(),
+            index: index.map(|i| i as u32),
+            remaining,
+        }
+    }
+}
+
+#[test]
+fn recovery_generate_test() {
+    let set = RecoveryCodes::generate_default();
+    let codes = set.codes();
+    let hashes = set.hashes();
+    println!("codes: {:?}", codes);
+    assert_eq!(10, codes.len());
+    assert_eq!(10, hashes.len());
+    for code in &codes {
+        assert_eq!(11, code.len());
+        assert_eq!(Some(5), code.find('-'));
+        assert!(
+            code.bytes()
+                .all(|b| b == b'-' || RECOVERY_ALPHABET.contains(&b))
+        );
+    }
+
+    let set = RecoveryCodes::generate(3, 4, 4).unwrap();
+    assert_eq!(3, set.codes().len());
+    assert_eq!(19, set.codes()[0].len());
+
+    assert!(RecoveryCodes::try_generate(0, 2, 5).is_err());
+    assert!(RecoveryCodes::try_generate(5, 0, 5).is_err());
+    assert!(RecoveryCodes::try_generate(5, 1, 4).is_err());
+    assert!(RecoveryCodes::try_generate(51, 2, 5).is_err());
+    assert!(RecoveryCodes::try_generate(5, 2, 33).is_err());
+    assert!(RecoveryCodes::try_generate(5, usize::MAX, 2).is_err());
+    assert!(RecoveryCodes::try_generate(1, 8, 8).is_ok());
+
+    // look-alike letters are read as the symbol they resemble
+    for (from, to) in LOOKALIKES {
+        assert!(!RECOVERY_ALPHABET.contains(&(from as u8)));
+        assert!(RECOVERY_ALPHABET.contains(&(to as u8)));
+    }
+    assert_eq!("0112558UA", RecoveryCodes::normalize("o-i l z s-5 b v a"));
+}
+
+#[test]
+fn recovery_verify_test() {
+    let set = RecoveryCodes::generate_default();
+    let codes = set.codes();
+    let hashes = set.hashes();
+
+    let typed = format!(" {} ", codes[3].to_lowercase().replace('-', " "));
+    let result = RecoveryCodes::verify(&typed, hashes.clone());
+    assert!(result.valid());
+    assert_eq!(Some(3), result.index());
+    assert_eq!(9, result.remaining().len());
+    assert!(!result.remaining().contains(&hashes[3]));
+
+    // consumed codes are rejected on the next attempt
+    let again = RecoveryCodes::verify(&codes[3], result.remaining());
+    assert!(!again.valid());
+    assert_eq!(None, again.index());
+    assert_eq!(9, again.remaining().len());
+
+    // a code typed with look-alike letters still matches
+    let typed = codes[5]
+        .replace('0', "O")
+        .replace('1', "l")
+        .replace('8', "B");
+    assert_eq!(
+        Some(5),
+        RecoveryCodes::verify(&typed, hashes.clone()).index()
+    );
+
+    let wrong = RecoveryCodes::verify("AAAAA-AAAAA", hashes.clone());
+    assert!(!wrong.valid());
+    assert_eq!(10, wrong.remaining().len());
+    assert!(!RecoveryCodes::verify("", hashes).valid());
+
+    // same code hashes differently each time, with the KDF settings recorded
+    let a = RecoveryCodes::hash_code("ABCDE-FGHJK").unwrap();
+    let b = RecoveryCodes::hash_code("abcde fghjk").unwrap();
+    assert_ne!(a, b);
+    assert!(a.starts_with("argon2id$m=19456,t=2,p=1$"));
+    assert!(RecoveryCodes::verify("abcdefghjk", vec!["broken".into(), a.clone(), b]).valid());
+
+    // other recorded settings are honoured, expensive ones are refused
+    let scrypt = KdfParams::try_scrypt(10, 8, 1).unwrap();
+    let c = RecoveryCodes::try_hash_code("ABCDE-FGHJK", &scrypt).unwrap();
+    assert!(RecoveryCodes::verify("abcdefghjk", vec![c]).valid());
+    let expensive = a.replacen("m=19456", "m=524288", 1);
+    assert!(!RecoveryCodes::verify("abcdefghjk", vec![expensive]).valid());
+    let tampered = a.replacen("t=2", "t=3", 1);
+    assert!(!RecoveryCodes::verify("abcdefghjk", vec![tampered]).valid());
+}
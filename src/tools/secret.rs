@@ -1,19 +1,44 @@
+use crate::tools::kdf::{DEFAULT_MAX_MEMORY_KIB, KdfParams};
+use crate::tools::keyhandle::KeyHandle;
+use crate::tools::random::SafeRandom;
+use aes_gcm::aes::cipher::{BlockCipher, BlockDecrypt, BlockEncrypt, BlockSizeUser, consts::U16};
+use aes_gcm::aes::{Aes128, Aes192, Aes256};
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use aes_gcm_siv::Aes256GcmSiv;
+use aes_kw::Kek;
 use chacha20poly1305::{
-    ChaCha20Poly1305, Key, Nonce, XChaCha20Poly1305, XNonce,
-    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, XChaCha20Poly1305,
+    aead::{Aead, AeadInPlace, KeyInit, Payload, generic_array::typenum::Unsigned},
 };
-use data_encoding::BASE64;
+use data_encoding::{BASE64, BASE64URL_NOPAD};
 
 use wasm_bindgen::prelude::*;
 
-fn decode_key(key_b64: &str) -> Result<Key, String> {
+const TAG_LEN: usize = 16;
+const ENVELOPE_MAGIC: &[u8; 2] = b"RT";
+const ENVELOPE_VERSION: u8 = 1;
+const PASSPHRASE_PREFIX: &str = "rtpw$1";
+const PASSPHRASE_SALT_LEN: usize = 16;
+const PASSPHRASE_ALGORITHM: AeadAlgorithm = AeadAlgorithm::XChaCha20Poly1305;
+
+fn decode_key(key_b64: &str, expected_len: usize) -> Result<Vec<u8>, String> {
+    decode_key_of(key_b64, &[expected_len])
+}
+
+/// `decode_key_of` decode a base64 key whose length must be one of `lens`
+fn decode_key_of(key_b64: &str, lens: &[usize]) -> Result<Vec<u8>, String> {
     let key_bytes = BASE64
         .decode(key_b64.as_bytes())
         .map_err(|_| "error: invalid base64 key")?;
-    let key_array: [u8; 32] = key_bytes
-        .try_into()
-        .map_err(|_| "error: key length must be 32 bytes")?;
-    Ok(key_array.into())
+    if !lens.contains(&key_bytes.len()) {
+        let lens: Vec<String> = lens.iter().map(|l| l.to_string()).collect();
+        let allowed = match lens.split_last() {
+            Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+            _ => lens.concat(),
+        };
+        return Err(format!("error: key length must be {} bytes", allowed));
+    }
+    Ok(key_bytes)
 }
 
 fn decode_nonce(nonce_b64: &str, expected_len: usize) -> Result<Vec<u8>, String> {
@@ -35,6 +60,371 @@ fn decode_ciphertext(ct_b64: &str) -> Result<Vec<u8>, String> {
         .map_err(|_| "error: invalid base64 ciphertext".into())
 }
 
+/// `aes_key_len` AES-GCM accepts 16 or 32 byte keys
+fn aes_key_len(key_b64: &str) -> Result<usize, String> {
+    decode_key_of(key_b64, &[16, 32]).map(|key| key.len())
+}
+
+/// `kek_wrap` AES-KW or AES-KWP with a concrete AES block cipher
+fn kek_wrap<Aes>(kek: &[u8], data: &[u8], padded: bool) -> Result<Vec<u8>, aes_kw::Error>
+where
+    Aes: KeyInit + BlockCipher + BlockSizeUser<BlockSize = U16> + BlockEncrypt + BlockDecrypt,
+{
+    let kek = Kek::<Aes>::try_from(kek)?;
+    if padded {
+        kek.wrap_with_padding_vec(data)
+    } else {
+        kek.wrap_vec(data)
+    }
+}
+
+/// `kek_unwrap` inverse of `kek_wrap`
+fn kek_unwrap<Aes>(kek: &[u8], wrapped: &[u8], padded: bool) -> Result<Vec<u8>, aes_kw::Error>
+where
+    Aes: KeyInit + BlockCipher + BlockSizeUser<BlockSize = U16> + BlockEncrypt + BlockDecrypt,
+{
+    let kek = Kek::<Aes>::try_from(kek)?;
+    if padded {
+        kek.unwrap_with_padding_vec(wrapped)
+    } else {
+        kek.unwrap_vec(wrapped)
+    }
+}
+
+/// `key_wrap` AES-KW (RFC 3394) or AES-KWP (RFC 5649) under a 128/192/256-bit KEK
+fn key_wrap(kek: &[u8], data: &[u8], padded: bool) -> Result<Vec<u8>, aes_kw::Error> {
+    match kek.len() {
+        16 => kek_wrap::<Aes128>(kek, data, padded),
+        24 => kek_wrap::<Aes192>(kek, data, padded),
+        _ => kek_wrap::<Aes256>(kek, data, padded),
+    }
+}
+
+/// `key_unwrap` inverse of `key_wrap`; fails if the integrity check value does not match
+fn key_unwrap(kek: &[u8], wrapped: &[u8], padded: bool) -> Result<Vec<u8>, aes_kw::Error> {
+    match kek.len() {
+        16 => kek_unwrap::<Aes128>(kek, wrapped, padded),
+        24 => kek_unwrap::<Aes192>(kek, wrapped, padded),
+        _ => kek_unwrap::<Aes256>(kek, wrapped, padded),
+    }
+}
+
+/// `aead_encrypt` encrypt with any AEAD cipher, returning base64 ciphertext || tag
+fn aead_encrypt<C: Aead + KeyInit>(
+    key_b64: &str,
+    nonce_b64: &str,
+    plaintext: &str,
+    aad: &[u8],
+) -> Result<String, String> {
+    let key = decode_key(key_b64, C::key_size())?;
+    let nonce = decode_nonce(nonce_b64, C::NonceSize::USIZE)?;
+    let cipher = C::new_from_slice(&key).map_err(|_| "error: invalid key")?;
+    let payload = Payload {
+        msg: plaintext.as_bytes(),
+        aad,
+    };
+    cipher
+        .encrypt(nonce.as_slice().into(), payload)
+        .map(|ct| BASE64.encode(&ct))
+        .map_err(|_| "error: encryption failed".into())
+}
+
+/// `aead_decrypt` decrypt base64 ciphertext || tag with any AEAD cipher
+fn aead_decrypt<C: Aead + KeyInit>(
+    key_b64: &str,
+    nonce_b64: &str,
+    ciphertext_b64: &str,
+    aad: &[u8],
+) -> Result<String, String> {
+    let key = decode_key(key_b64, C::key_size())?;
+    let nonce = decode_nonce(nonce_b64, C::NonceSize::USIZE)?;
+    let ciphertext = decode_ciphertext(ciphertext_b64)?;
+    let cipher = C::new_from_slice(&key).map_err(|_| "error: invalid key")?;
+    let payload = Payload {
+        msg: &ciphertext,
+        aad,
+    };
+    let plaintext = cipher
+        .decrypt(nonce.as_slice().into(), payload)
+        .map_err(|_| "error: decryption failed")?;
+    String::from_utf8(plaintext).map_err(|_| "error: plaintext is not valid utf-8".into())
+}
+
+/// `aead_check` validate raw key and nonce lengths for a cipher
+fn aead_check<C: AeadInPlace + KeyInit>(key: &[u8], nonce: &[u8]) -> Result<C, String> {
+    if key.len() != C::key_size() {
+        return Err(format!("error: key length must be {} bytes", C::key_size()));
+    }
+    if nonce.len() != C::NonceSize::USIZE {
+        return Err(format!(
+            "error: nonce length must be {} bytes",
+            C::NonceSize::USIZE
+        ));
+    }
+    C::new_from_slice(key).map_err(|_| "error: invalid key".into())
+}
+
+/// `aead_seal_detached` encrypt raw bytes, returning (ciphertext, tag)
+fn aead_seal_detached<C: AeadInPlace + KeyInit>(
+    key: &[u8],
+    nonce: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let cipher = aead_check::<C>(key, nonce)?;
+    let mut buffer = plaintext.to_vec();
+    let tag = cipher
+        .encrypt_in_place_detached(nonce.into(), aad, &mut buffer)
+        .map_err(|_| "error: encryption failed")?;
+    Ok((buffer, tag.to_vec()))
+}
+
+/// `aead_open_detached` decrypt raw ciphertext with a separate tag
+fn aead_open_detached<C: AeadInPlace + KeyInit>(
+    key: &[u8],
+    nonce: &[u8],
+    ciphertext: &[u8],
+    tag: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, String> {
+    let cipher = aead_check::<C>(key, nonce)?;
+    if tag.len() != C::TagSize::USIZE {
+        return Err(format!(
+            "error: tag length must be {} bytes",
+            C::TagSize::USIZE
+        ));
+    }
+    let mut buffer = ciphertext.to_vec();
+    cipher
+        .decrypt_in_place_detached(nonce.into(), aad, &mut buffer, tag.into())
+        .map_err(|_| "error: decryption failed")?;
+    Ok(buffer)
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// AEAD algorithm for the byte-oriented `Secret` APIs
+pub enum AeadAlgorithm {
+    /// ChaCha20-Poly1305 (32-byte key, 12-byte nonce)
+    ChaCha20Poly1305 = 0,
+    /// XChaCha20-Poly1305 (32-byte key, 24-byte nonce)
+    XChaCha20Poly1305 = 1,
+    /// AES-128-GCM (16-byte key, 12-byte nonce)
+    Aes128Gcm = 2,
+    /// AES-256-GCM (32-byte key, 12-byte nonce)
+    Aes256Gcm = 3,
+    /// AES-256-GCM-SIV (32-byte key, 12-byte nonce)
+    Aes256GcmSiv = 4,
+}
+
+impl AeadAlgorithm {
+    pub(crate) fn from_id(id: u8) -> Option<AeadAlgorithm> {
+        match id {
+            0 => Some(AeadAlgorithm::ChaCha20Poly1305),
+            1 => Some(AeadAlgorithm::XChaCha20Poly1305),
+            2 => Some(AeadAlgorithm::Aes128Gcm),
+            3 => Some(AeadAlgorithm::Aes256Gcm),
+            4 => Some(AeadAlgorithm::Aes256GcmSiv),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn key_len(&self) -> usize {
+        match self {
+            AeadAlgorithm::Aes128Gcm => 16,
+            _ => 32,
+        }
+    }
+
+    pub(crate) fn nonce_len(&self) -> usize {
+        match self {
+            AeadAlgorithm::XChaCha20Poly1305 => 24,
+            _ => 12,
+        }
+    }
+
+    pub(crate) fn seal_detached(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>), String> {
+        match self {
+            AeadAlgorithm::ChaCha20Poly1305 => {
+                aead_seal_detached::<ChaCha20Poly1305>(key, nonce, plaintext, aad)
+            }
+            AeadAlgorithm::XChaCha20Poly1305 => {
+                aead_seal_detached::<XChaCha20Poly1305>(key, nonce, plaintext, aad)
+            }
+            AeadAlgorithm::Aes128Gcm => aead_seal_detached::<Aes128Gcm>(key, nonce, plaintext, aad),
+            AeadAlgorithm::Aes256Gcm => aead_seal_detached::<Aes256Gcm>(key, nonce, plaintext, aad),
+            AeadAlgorithm::Aes256GcmSiv => {
+                aead_seal_detached::<Aes256GcmSiv>(key, nonce, plaintext, aad)
+            }
+        }
+    }
+
+    pub(crate) fn open_detached(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        match self {
+            AeadAlgorithm::ChaCha20Poly1305 => {
+                aead_open_detached::<ChaCha20Poly1305>(key, nonce, ciphertext, tag, aad)
+            }
+            AeadAlgorithm::XChaCha20Poly1305 => {
+                aead_open_detached::<XChaCha20Poly1305>(key, nonce, ciphertext, tag, aad)
+            }
+            AeadAlgorithm::Aes128Gcm => {
+                aead_open_detached::<Aes128Gcm>(key, nonce, ciphertext, tag, aad)
+            }
+            AeadAlgorithm::Aes256Gcm => {
+                aead_open_detached::<Aes256Gcm>(key, nonce, ciphertext, tag, aad)
+            }
+            AeadAlgorithm::Aes256GcmSiv => {
+                aead_open_detached::<Aes256GcmSiv>(key, nonce, ciphertext, tag, aad)
+            }
+        }
+    }
+
+    /// ciphertext || tag
+    pub(crate) fn seal(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let (mut ciphertext, tag) = self.seal_detached(key, nonce, plaintext, aad)?;
+        ciphertext.extend(tag);
+        Ok(ciphertext)
+    }
+
+    /// open ciphertext || tag
+    pub(crate) fn open(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        sealed: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let split = sealed
+            .len()
+            .checked_sub(TAG_LEN)
+            .ok_or("error: ciphertext is shorter than the tag")?;
+        self.open_detached(key, nonce, &sealed[..split], &sealed[split..], aad)
+    }
+}
+
+#[wasm_bindgen]
+/// `DetachedCiphertext` ciphertext and authentication tag stored apart
+pub struct DetachedCiphertext {
+    ciphertext: Vec<u8>,
+    tag: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl DetachedCiphertext {
+    /// `ciphertext` encrypted bytes, same length as the plaintext
+    pub fn ciphertext(&self) -> Vec<u8> {
+        self.ciphertext.clone()
+    }
+
+    /// `tag` 16-byte authentication tag
+    pub fn tag(&self) -> Vec<u8> {
+        self.tag.clone()
+    }
+}
+
+#[wasm_bindgen]
+/// `EnvelopeHeader` public fields of a sealed envelope
+pub struct EnvelopeHeader {
+    version: u8,
+    algorithm: AeadAlgorithm,
+    key_id: String,
+    nonce: Vec<u8>,
+    /// encoded header length, set when parsing
+    len: usize,
+}
+
+#[wasm_bindgen]
+impl EnvelopeHeader {
+    /// `version` envelope format version
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// `algorithm` AEAD algorithm used to seal
+    pub fn algorithm(&self) -> AeadAlgorithm {
+        self.algorithm
+    }
+
+    /// `key_id` identifier of the key to open with
+    pub fn key_id(&self) -> String {
+        self.key_id.clone()
+    }
+
+    /// `nonce` random nonce picked when sealing
+    pub fn nonce(&self) -> Vec<u8> {
+        self.nonce.clone()
+    }
+}
+
+impl EnvelopeHeader {
+    /// `parse` read `magic || version || alg || key id len || key id || nonce`
+    pub(crate) fn parse(envelope: &[u8]) -> Result<EnvelopeHeader, String> {
+        if envelope.len() < 5 || &envelope[..2] != ENVELOPE_MAGIC {
+            return Err("error: not a sealed envelope".into());
+        }
+        let version = envelope[2];
+        if version != ENVELOPE_VERSION {
+            return Err(format!("error: unsupported envelope version {}", version));
+        }
+        let algorithm = AeadAlgorithm::from_id(envelope[3])
+            .ok_or_else(|| format!("error: unknown algorithm id {}", envelope[3]))?;
+        let key_id_end = 5 + envelope[4] as usize;
+        let nonce_end = key_id_end + algorithm.nonce_len();
+        if envelope.len() < nonce_end + TAG_LEN {
+            return Err("error: envelope is truncated".into());
+        }
+        let key_id = String::from_utf8(envelope[5..key_id_end].to_vec())
+            .map_err(|_| "error: key id is not valid utf-8")?;
+        Ok(EnvelopeHeader {
+            version,
+            algorithm,
+            key_id,
+            nonce: envelope[key_id_end..nonce_end].to_vec(),
+            len: nonce_end,
+        })
+    }
+
+    /// `encode` serialize the header; it is also the associated data
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut out = ENVELOPE_MAGIC.to_vec();
+        out.push(self.version);
+        out.push(self.algorithm as u8);
+        out.push(self.key_id.len() as u8);
+        out.extend_from_slice(self.key_id.as_bytes());
+        out.extend_from_slice(&self.nonce);
+        out
+    }
+}
+
+/// fields of a `rtpw$1$...` passphrase ciphertext
+struct PassphraseCiphertext<'a> {
+    params: KdfParams,
+    /// authenticated prefix up to and including the salt
+    header: &'a str,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    sealed: Vec<u8>,
+}
+
 #[wasm_bindgen]
 /// `Secret` encryption / decryption
 pub struct Secret {}
@@ -43,108 +433,541 @@ pub struct Secret {}
 impl Secret {
     /// `xchacha_encrypt` plaintext with given key_b64 (32 bytes) and nonce_b64 (24 bytes).
     pub fn xchacha_encrypt(key_b64: &str, nonce_b64: &str, plaintext: &str) -> Option<String> {
-        let key = match decode_key(key_b64) {
-            Ok(k) => k,
-            Err(_) => return None,
-        };
-        let nonce_bytes = match decode_nonce(nonce_b64, 24) {
-            Ok(n) => n,
-            Err(_) => return None,
-        };
-
-        let cipher = XChaCha20Poly1305::new(&key);
-        let nonce_array: [u8; 24] = match nonce_bytes.try_into() {
-            Ok(arr) => arr,
-            Err(_) => return None,
-        };
-        let nonce = XNonce::from(nonce_array);
-        match cipher.encrypt(&nonce, plaintext.as_bytes()) {
-            Ok(ct) => Some(BASE64.encode(&ct)),
-            Err(_) => return None,
-        }
+        aead_encrypt::<XChaCha20Poly1305>(key_b64, nonce_b64, plaintext, b"").ok()
     }
 
     /// `xchacha_decrypt` ciphertext_b64 with given key_b64 and nonce_b64.
     pub fn xchacha_decrypt(key_b64: &str, nonce_b64: &str, ciphertext_b64: &str) -> Option<String> {
-        let key = match decode_key(key_b64) {
-            Ok(k) => k,
-            Err(_) => return None,
-        };
-        let nonce_bytes = match decode_nonce(nonce_b64, 24) {
-            Ok(n) => n,
-            Err(_) => return None,
-        };
-        let ciphertext = match decode_ciphertext(ciphertext_b64) {
-            Ok(c) => c,
-            Err(_) => return None,
-        };
-
-        let cipher = XChaCha20Poly1305::new(&key);
-        let nonce_array: [u8; 24] = match nonce_bytes.try_into() {
-            Ok(arr) => arr,
-            Err(_) => return None,
-        };
-        let nonce = XNonce::from(nonce_array);
-
-        match cipher.decrypt(&nonce, ciphertext.as_ref()) {
-            Ok(pt) => match String::from_utf8(pt) {
-                Ok(s) => Some(s),
-                Err(_) => return None,
-            },
-            Err(_) => return None,
-        }
+        aead_decrypt::<XChaCha20Poly1305>(key_b64, nonce_b64, ciphertext_b64, b"").ok()
     }
 
     /// `chacha_encrypt` plaintext with given key_b64 (32 bytes) and nonce_b64 (12 bytes).
     pub fn chacha_encrypt(key_b64: &str, nonce_b64: &str, plaintext: &str) -> Option<String> {
-        let key = match decode_key(key_b64) {
-            Ok(k) => k,
-            Err(_) => return None,
-        };
-        let nonce_bytes = match decode_nonce(nonce_b64, 12) {
-            Ok(n) => n,
-            Err(_) => return None,
-        };
-        let cipher = ChaCha20Poly1305::new(&key);
-        let nonce_array: [u8; 12] = match nonce_bytes.try_into() {
-            Ok(arr) => arr,
-            Err(_) => return None,
-        };
-        let nonce = Nonce::from(nonce_array);
-
-        match cipher.encrypt(&nonce, plaintext.as_bytes()) {
-            Ok(ct) => Some(BASE64.encode(&ct)),
-            Err(_) => return None,
-        }
+        aead_encrypt::<ChaCha20Poly1305>(key_b64, nonce_b64, plaintext, b"").ok()
     }
 
     /// `chacha_decrypt` ciphertext_b64 with given key_b64 and nonce_b64.
     pub fn chacha_decrypt(key_b64: &str, nonce_b64: &str, ciphertext_b64: &str) -> Option<String> {
-        let key = match decode_key(key_b64) {
-            Ok(k) => k,
-            Err(_) => return None,
-        };
-        let nonce_bytes = match decode_nonce(nonce_b64, 12) {
-            Ok(n) => n,
-            Err(_) => return None,
-        };
-        let ciphertext = match decode_ciphertext(ciphertext_b64) {
-            Ok(c) => c,
-            Err(_) => return None,
+        aead_decrypt::<ChaCha20Poly1305>(key_b64, nonce_b64, ciphertext_b64, b"").ok()
+    }
+
+    /// `aes_gcm_encrypt` plaintext with AES-GCM, key_b64 (16 bytes for AES-128, 32 for AES-256) and nonce_b64 (12 bytes).
+    ///
+    /// Output is ciphertext || 16-byte tag, the same layout as WebCrypto `AES-GCM`.
+    pub fn aes_gcm_encrypt(
+        key_b64: &str,
+        nonce_b64: &str,
+        plaintext: &str,
+    ) -> Result<String, JsValue> {
+        Self::try_aes_gcm_encrypt(key_b64, nonce_b64, plaintext, b"")
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `aes_gcm_decrypt` ciphertext_b64 with AES-GCM, key_b64 (16 or 32 bytes) and nonce_b64 (12 bytes).
+    pub fn aes_gcm_decrypt(
+        key_b64: &str,
+        nonce_b64: &str,
+        ciphertext_b64: &str,
+    ) -> Result<String, JsValue> {
+        Self::try_aes_gcm_decrypt(key_b64, nonce_b64, ciphertext_b64, b"")
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `aes_gcm_siv_encrypt` plaintext with AES-256-GCM-SIV (RFC 8452), key_b64 (32 bytes) and nonce_b64 (12 bytes).
+    pub fn aes_gcm_siv_encrypt(
+        key_b64: &str,
+        nonce_b64: &str,
+        plaintext: &str,
+    ) -> Result<String, JsValue> {
+        aead_encrypt::<Aes256GcmSiv>(key_b64, nonce_b64, plaintext, b"")
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `aes_gcm_siv_decrypt` ciphertext_b64 with AES-256-GCM-SIV, key_b64 (32 bytes) and nonce_b64 (12 bytes).
+    pub fn aes_gcm_siv_decrypt(
+        key_b64: &str,
+        nonce_b64: &str,
+        ciphertext_b64: &str,
+    ) -> Result<String, JsValue> {
+        aead_decrypt::<Aes256GcmSiv>(key_b64, nonce_b64, ciphertext_b64, b"")
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `xchacha_encrypt_aad` plaintext bound to associated data `aad`, key_b64 (32 bytes) and nonce_b64 (24 bytes).
+    pub fn xchacha_encrypt_aad(
+        key_b64: &str,
+        nonce_b64: &str,
+        plaintext: &str,
+        aad: &[u8],
+    ) -> Result<String, JsValue> {
+        aead_encrypt::<XChaCha20Poly1305>(key_b64, nonce_b64, plaintext, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `xchacha_decrypt_aad` ciphertext_b64 with the same associated data used to encrypt.
+    pub fn xchacha_decrypt_aad(
+        key_b64: &str,
+        nonce_b64: &str,
+        ciphertext_b64: &str,
+        aad: &[u8],
+    ) -> Result<String, JsValue> {
+        aead_decrypt::<XChaCha20Poly1305>(key_b64, nonce_b64, ciphertext_b64, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `chacha_encrypt_aad` plaintext bound to associated data `aad`, key_b64 (32 bytes) and nonce_b64 (12 bytes).
+    pub fn chacha_encrypt_aad(
+        key_b64: &str,
+        nonce_b64: &str,
+        plaintext: &str,
+        aad: &[u8],
+    ) -> Result<String, JsValue> {
+        aead_encrypt::<ChaCha20Poly1305>(key_b64, nonce_b64, plaintext, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `chacha_decrypt_aad` ciphertext_b64 with the same associated data used to encrypt.
+    pub fn chacha_decrypt_aad(
+        key_b64: &str,
+        nonce_b64: &str,
+        ciphertext_b64: &str,
+        aad: &[u8],
+    ) -> Result<String, JsValue> {
+        aead_decrypt::<ChaCha20Poly1305>(key_b64, nonce_b64, ciphertext_b64, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `aes_gcm_encrypt_aad` plaintext bound to associated data `aad` (WebCrypto `additionalData`).
+    pub fn aes_gcm_encrypt_aad(
+        key_b64: &str,
+        nonce_b64: &str,
+        plaintext: &str,
+        aad: &[u8],
+    ) -> Result<String, JsValue> {
+        Self::try_aes_gcm_encrypt(key_b64, nonce_b64, plaintext, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `aes_gcm_decrypt_aad` ciphertext_b64 with the same associated data used to encrypt.
+    pub fn aes_gcm_decrypt_aad(
+        key_b64: &str,
+        nonce_b64: &str,
+        ciphertext_b64: &str,
+        aad: &[u8],
+    ) -> Result<String, JsValue> {
+        Self::try_aes_gcm_decrypt(key_b64, nonce_b64, ciphertext_b64, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `aes_gcm_siv_encrypt_aad` plaintext bound to associated data `aad` with AES-256-GCM-SIV.
+    pub fn aes_gcm_siv_encrypt_aad(
+        key_b64: &str,
+        nonce_b64: &str,
+        plaintext: &str,
+        aad: &[u8],
+    ) -> Result<String, JsValue> {
+        aead_encrypt::<Aes256GcmSiv>(key_b64, nonce_b64, plaintext, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `aes_gcm_siv_decrypt_aad` ciphertext_b64 with the same associated data used to encrypt.
+    pub fn aes_gcm_siv_decrypt_aad(
+        key_b64: &str,
+        nonce_b64: &str,
+        ciphertext_b64: &str,
+        aad: &[u8],
+    ) -> Result<String, JsValue> {
+        aead_decrypt::<Aes256GcmSiv>(key_b64, nonce_b64, ciphertext_b64, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `encrypt_bytes` encrypt raw bytes, returning ciphertext || 16-byte tag
+    ///
+    /// ### Arguments
+    /// * `algorithm` - AEAD algorithm
+    /// * `key` - Key bytes (16 bytes for AES-128-GCM, 32 otherwise)
+    /// * `nonce` - Nonce bytes (24 bytes for XChaCha20-Poly1305, 12 otherwise)
+    /// * `plaintext` - Data to encrypt
+    /// * `aad` - Associated data, may be empty
+    pub fn encrypt_bytes(
+        algorithm: AeadAlgorithm,
+        key: &[u8],
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        algorithm
+            .seal(key, nonce, plaintext, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `decrypt_bytes` decrypt ciphertext || tag produced by `encrypt_bytes`
+    pub fn decrypt_bytes(
+        algorithm: AeadAlgorithm,
+        key: &[u8],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        algorithm
+            .open(key, nonce, ciphertext, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `encrypt_detached` encrypt raw bytes, returning the ciphertext and tag separately
+    pub fn encrypt_detached(
+        algorithm: AeadAlgorithm,
+        key: &[u8],
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<DetachedCiphertext, JsValue> {
+        let (ciphertext, tag) = algorithm
+            .seal_detached(key, nonce, plaintext, aad)
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(DetachedCiphertext { ciphertext, tag })
+    }
+
+    /// `decrypt_detached` decrypt a ciphertext with its separately stored tag
+    pub fn decrypt_detached(
+        algorithm: AeadAlgorithm,
+        key: &[u8],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        algorithm
+            .open_detached(key, nonce, ciphertext, tag, aad)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `try_seal` encrypt into a self-describing envelope with a random nonce
+    pub(crate) fn try_seal(
+        algorithm: AeadAlgorithm,
+        key: &[u8],
+        key_id: &str,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        if key_id.len() > u8::MAX as usize {
+            return Err("error: key id must be at most 255 bytes".into());
+        }
+        let header = EnvelopeHeader {
+            version: ENVELOPE_VERSION,
+            algorithm,
+            key_id: key_id.to_string(),
+            nonce: SafeRandom::gen_bytes(algorithm.nonce_len()).raw(),
+            len: 0,
         };
+        let aad = header.encode();
+        let sealed = algorithm.seal(key, &header.nonce, plaintext, &aad)?;
+        Ok([aad, sealed].concat())
+    }
+
+    /// `try_open` authenticate and decrypt an envelope
+    pub(crate) fn try_open(key: &[u8], envelope: &[u8]) -> Result<Vec<u8>, String> {
+        let header = EnvelopeHeader::parse(envelope)?;
+        let (aad, sealed) = envelope.split_at(header.len);
+        header.algorithm.open(key, &header.nonce, sealed, aad)
+    }
+
+    /// `seal` encrypt into a binary envelope: `"RT" || version || alg || key id || nonce || ciphertext || tag`
+    ///
+    /// ### Arguments
+    /// * `algorithm` - AEAD algorithm
+    /// * `key` - Key bytes for the algorithm
+    /// * `key_id` - Identifier stored in clear to pick the key when opening (max 255 bytes)
+    /// * `plaintext` - Data to encrypt
+    ///
+    /// The header is authenticated as associated data and the nonce is generated internally.
+    pub fn seal(
+        algorithm: AeadAlgorithm,
+        key: &[u8],
+        key_id: &str,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::try_seal(algorithm, key, key_id, plaintext).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `seal_text` same as `seal`, as a base64url (no padding) string
+    pub fn seal_text(
+        algorithm: AeadAlgorithm,
+        key: &[u8],
+        key_id: &str,
+        plaintext: &[u8],
+    ) -> Result<String, JsValue> {
+        Ok(BASE64URL_NOPAD.encode(&Self::seal(algorithm, key, key_id, plaintext)?))
+    }
+
+    /// `open` decrypt a binary envelope produced by `seal`
+    pub fn open(key: &[u8], envelope: &[u8]) -> Result<Vec<u8>, JsValue> {
+        Self::try_open(key, envelope).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `open_text` decrypt a base64url envelope produced by `seal_text`
+    pub fn open_text(key: &[u8], envelope: &str) -> Result<Vec<u8>, JsValue> {
+        BASE64URL_NOPAD
+            .decode(envelope.as_bytes())
+            .map_err(|_| "error: invalid base64url envelope".to_string())
+            .and_then(|envelope| Self::try_open(key, &envelope))
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `encrypt_bytes_with_handle` same as `encrypt_bytes`, with the key held in a `KeyHandle`
+    pub fn encrypt_bytes_with_handle(
+        algorithm: AeadAlgorithm,
+        key: &KeyHandle,
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::encrypt_bytes(algorithm, key.bytes(), nonce, plaintext, aad)
+    }
+
+    /// `decrypt_bytes_with_handle` same as `decrypt_bytes`, with the key held in a `KeyHandle`
+    pub fn decrypt_bytes_with_handle(
+        algorithm: AeadAlgorithm,
+        key: &KeyHandle,
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::decrypt_bytes(algorithm, key.bytes(), nonce, ciphertext, aad)
+    }
 
-        let cipher = ChaCha20Poly1305::new(&key);
-        let nonce_array: [u8; 12] = match nonce_bytes.try_into() {
-            Ok(arr) => arr,
-            Err(_) => return None,
+    /// `seal_with_handle` same as `seal`, with the key held in a `KeyHandle`
+    pub fn seal_with_handle(
+        algorithm: AeadAlgorithm,
+        key: &KeyHandle,
+        key_id: &str,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        Self::seal(algorithm, key.bytes(), key_id, plaintext)
+    }
+
+    /// `open_with_handle` same as `open`, with the key held in a `KeyHandle`
+    pub fn open_with_handle(key: &KeyHandle, envelope: &[u8]) -> Result<Vec<u8>, JsValue> {
+        Self::open(key.bytes(), envelope)
+    }
+
+    /// `envelope_header` read the algorithm and key id of a binary envelope without decrypting
+    pub fn envelope_header(envelope: &[u8]) -> Result<EnvelopeHeader, JsValue> {
+        EnvelopeHeader::parse(envelope).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `try_encrypt_with_passphrase` derive a key from the passphrase and seal with XChaCha20-Poly1305
+    pub(crate) fn try_encrypt_with_passphrase(
+        passphrase: &str,
+        plaintext: &str,
+        params: &KdfParams,
+    ) -> Result<String, String> {
+        if passphrase.is_empty() {
+            return Err("error: passphrase is required".into());
+        }
+        let salt = SafeRandom::gen_bytes(PASSPHRASE_SALT_LEN).raw();
+        let nonce = SafeRandom::gen_bytes(PASSPHRASE_ALGORITHM.nonce_len()).raw();
+        let key = params.derive(passphrase.as_bytes(), &salt, PASSPHRASE_ALGORITHM.key_len())?;
+        // everything before the nonce is authenticated, so the KDF settings cannot be swapped
+        let header = format!(
+            "{}${}${}",
+            PASSPHRASE_PREFIX,
+            params.encode(),
+            BASE64URL_NOPAD.encode(&salt)
+        );
+        let sealed =
+            PASSPHRASE_ALGORITHM.seal(&key, &nonce, plaintext.as_bytes(), header.as_bytes())?;
+        Ok(format!(
+            "{}${}${}",
+            header,
+            BASE64URL_NOPAD.encode(&nonce),
+            BASE64URL_NOPAD.encode(&sealed)
+        ))
+    }
+
+    /// `parse_passphrase` split `rtpw$1$<kdf>$<params>$<salt>$<nonce>$<ciphertext>`
+    fn parse_passphrase(encoded: &str) -> Result<PassphraseCiphertext<'_>, String> {
+        let fields: Vec<&str> = encoded.split('$').collect();
+        if fields.len() != 7 || format!("{}${}", fields[0], fields[1]) != PASSPHRASE_PREFIX {
+            return Err("error: not a passphrase ciphertext".into());
+        }
+        let header_len = encoded.len() - fields[5].len() - fields[6].len() - 2;
+        let decode = |field: &str, name: &str| {
+            BASE64URL_NOPAD
+                .decode(field.as_bytes())
+                .map_err(|_| format!("error: invalid base64url {}", name))
         };
-        let nonce = Nonce::from(nonce_array);
-        match cipher.decrypt(&nonce, ciphertext.as_ref()) {
-            Ok(pt) => match String::from_utf8(pt) {
-                Ok(s) => Some(s),
-                Err(_) => return None,
-            },
-            Err(_) => return None,
+        Ok(PassphraseCiphertext {
+            params: KdfParams::parse(fields[2], fields[3])?,
+            header: &encoded[..header_len],
+            salt: decode(fields[4], "salt")?,
+            nonce: decode(fields[5], "nonce")?,
+            sealed: decode(fields[6], "ciphertext")?,
+        })
+    }
+
+    /// `try_decrypt_with_passphrase` re-derive the key with the recorded parameters (at most 256 MiB) and open
+    pub(crate) fn try_decrypt_with_passphrase(
+        passphrase: &str,
+        encoded: &str,
+    ) -> Result<String, String> {
+        Self::try_decrypt_with_passphrase_limit(passphrase, encoded, DEFAULT_MAX_MEMORY_KIB)
+    }
+
+    /// `try_decrypt_with_passphrase_limit` as `try_decrypt_with_passphrase`, refusing KDF settings above `max_memory_kib`
+    pub(crate) fn try_decrypt_with_passphrase_limit(
+        passphrase: &str,
+        encoded: &str,
+        max_memory_kib: u64,
+    ) -> Result<String, String> {
+        let parsed = Self::parse_passphrase(encoded)?;
+        parsed.params.check_memory(max_memory_kib)?;
+        let key = parsed.params.derive(
+            passphrase.as_bytes(),
+            &parsed.salt,
+            PASSPHRASE_ALGORITHM.key_len(),
+        )?;
+        let plaintext = PASSPHRASE_ALGORITHM
+            .open(
+                &key,
+                &parsed.nonce,
+                &parsed.sealed,
+                parsed.header.as_bytes(),
+            )
+            .map_err(|_| "error: wrong passphrase or corrupted data")?;
+        String::from_utf8(plaintext).map_err(|_| "error: plaintext is not valid utf-8".into())
+    }
+
+    /// `encrypt_with_passphrase` encrypt text with a key derived from a passphrase
+    ///
+    /// ### Arguments
+    /// * `passphrase` - User passphrase
+    /// * `plaintext` - Text to encrypt
+    /// * `params` - Argon2id or scrypt parameters, e.g. `KdfParams::recommended()`
+    ///
+    /// ### Returns
+    /// `rtpw$1$<kdf>$<params>$<salt>$<nonce>$<ciphertext>` with base64url fields
+    pub fn encrypt_with_passphrase(
+        passphrase: &str,
+        plaintext: &str,
+        params: &KdfParams,
+    ) -> Result<String, JsValue> {
+        Self::try_encrypt_with_passphrase(passphrase, plaintext, params)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `decrypt_with_passphrase` decrypt the output of `encrypt_with_passphrase`, refusing KDF settings above 256 MiB
+    pub fn decrypt_with_passphrase(passphrase: &str, encoded: &str) -> Result<String, JsValue> {
+        Self::try_decrypt_with_passphrase(passphrase, encoded).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `decrypt_with_passphrase_limit` decrypt only if the recorded KDF needs at most `max_memory_kib` KiB
+    ///
+    /// `decrypt_with_passphrase` allows 256 MiB; pass a larger budget for stronger settings, or a
+    /// smaller one for ciphertexts from untrusted sources, e.g. `KdfParams::recommended().memory_kib()`.
+    pub fn decrypt_with_passphrase_limit(
+        passphrase: &str,
+        encoded: &str,
+        max_memory_kib: u64,
+    ) -> Result<String, JsValue> {
+        Self::try_decrypt_with_passphrase_limit(passphrase, encoded, max_memory_kib)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `passphrase_params` KDF parameters recorded in a passphrase ciphertext, to decide on re-encryption
+    pub fn passphrase_params(encoded: &str) -> Result<KdfParams, JsValue> {
+        Self::parse_passphrase(encoded)
+            .map(|parsed| parsed.params)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `try_key_wrap` wrap base64 key material under a base64 KEK
+    pub(crate) fn try_key_wrap(
+        kek_b64: &str,
+        key_b64: &str,
+        padded: bool,
+    ) -> Result<String, String> {
+        let kek = decode_key_of(kek_b64, &[16, 24, 32])?;
+        let key = BASE64
+            .decode(key_b64.as_bytes())
+            .map_err(|_| "error: invalid base64 key to wrap")?;
+        if !padded && (key.len() < 16 || key.len() % 8 != 0) {
+            return Err(
+                "error: key to wrap must be a multiple of 8 bytes and at least 16 bytes".into(),
+            );
+        }
+        if key.is_empty() {
+            return Err("error: key to wrap must not be empty".into());
+        }
+        key_wrap(&kek, &key, padded)
+            .map(|wrapped| BASE64.encode(&wrapped))
+            .map_err(|e| format!("error: {}", e))
+    }
+
+    /// `try_key_unwrap` unwrap base64 wrapped key material
+    pub(crate) fn try_key_unwrap(
+        kek_b64: &str,
+        wrapped_b64: &str,
+        padded: bool,
+    ) -> Result<String, String> {
+        let kek = decode_key_of(kek_b64, &[16, 24, 32])?;
+        let wrapped = BASE64
+            .decode(wrapped_b64.as_bytes())
+            .map_err(|_| "error: invalid base64 wrapped key")?;
+        key_unwrap(&kek, &wrapped, padded)
+            .map(|key| BASE64.encode(&key))
+            .map_err(|_| "error: key unwrap failed".into())
+    }
+
+    /// `aes_key_wrap` wrap a data key with AES Key Wrap (RFC 3394), as WebCrypto `AES-KW` `wrapKey`
+    ///
+    /// ### Arguments
+    /// * `kek_b64` - Key-encryption key, 16, 24 or 32 bytes
+    /// * `key_b64` - Key to wrap, a multiple of 8 bytes and at least 16 bytes
+    ///
+    /// ### Returns
+    /// Base64 wrapped key, 8 bytes longer than the input
+    pub fn aes_key_wrap(kek_b64: &str, key_b64: &str) -> Result<String, JsValue> {
+        Self::try_key_wrap(kek_b64, key_b64, false).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `aes_key_unwrap` unwrap an RFC 3394 wrapped key; fails if the KEK is wrong or the data was modified
+    pub fn aes_key_unwrap(kek_b64: &str, wrapped_b64: &str) -> Result<String, JsValue> {
+        Self::try_key_unwrap(kek_b64, wrapped_b64, false).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `aes_key_wrap_pad` wrap key material of any length with AES Key Wrap with Padding (RFC 5649)
+    pub fn aes_key_wrap_pad(kek_b64: &str, key_b64: &str) -> Result<String, JsValue> {
+        Self::try_key_wrap(kek_b64, key_b64, true).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `aes_key_unwrap_pad` unwrap an RFC 5649 wrapped key
+    pub fn aes_key_unwrap_pad(kek_b64: &str, wrapped_b64: &str) -> Result<String, JsValue> {
+        Self::try_key_unwrap(kek_b64, wrapped_b64, true).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `try_aes_gcm_encrypt` pick AES-128 or AES-256 from the key length
+    fn try_aes_gcm_encrypt(
+        key_b64: &str,
+        nonce_b64: &str,
+        plaintext: &str,
+        aad: &[u8],
+    ) -> Result<String, String> {
+        match aes_key_len(key_b64)? {
+            16 => aead_encrypt::<Aes128Gcm>(key_b64, nonce_b64, plaintext, aad),
+            _ => aead_encrypt::<Aes256Gcm>(key_b64, nonce_b64, plaintext, aad),
+        }
+    }
+
+    /// `try_aes_gcm_decrypt` pick AES-128 or AES-256 from the key length
+    fn try_aes_gcm_decrypt(
+        key_b64: &str,
+        nonce_b64: &str,
+        ciphertext_b64: &str,
+        aad: &[u8],
+    ) -> Result<String, String> {
+        match aes_key_len(key_b64)? {
+            16 => aead_decrypt::<Aes128Gcm>(key_b64, nonce_b64, ciphertext_b64, aad),
+            _ => aead_decrypt::<Aes256Gcm>(key_b64, nonce_b64, ciphertext_b64, aad),
         }
     }
 }
@@ -184,3 +1007,440 @@ fn xchacha_test() {
 
     assert_eq!(plaintext, dec.unwrap());
 }
+
+#[test]
+fn aes_gcm_test() {
+    // NIST GCM test cases 2 and 14: zero key, zero nonce, 16 zero bytes
+    let nonce_b64 = BASE64.encode(&[0u8; 12]);
+    let plaintext = "\0".repeat(16);
+
+    let key128 = BASE64.encode(&[0u8; 16]);
+    let enc = Secret::aes_gcm_encrypt(&key128, &nonce_b64, &plaintext).unwrap();
+    let expected = data_encoding::HEXLOWER
+        .decode(b"0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf")
+        .unwrap();
+    assert_eq!(BASE64.encode(&expected), enc);
+    assert_eq!(
+        plaintext,
+        Secret::aes_gcm_decrypt(&key128, &nonce_b64, &enc).unwrap()
+    );
+
+    let key256 = BASE64.encode(&[0u8; 32]);
+    let enc = Secret::aes_gcm_encrypt(&key256, &nonce_b64, &plaintext).unwrap();
+    let expected = data_encoding::HEXLOWER
+        .decode(b"cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919")
+        .unwrap();
+    assert_eq!(BASE64.encode(&expected), enc);
+
+    let text = "Hello AES-GCM interop with WebCrypto!";
+    let enc = Secret::aes_gcm_encrypt(&key256, &nonce_b64, text).unwrap();
+    assert_eq!(
+        text,
+        Secret::aes_gcm_decrypt(&key256, &nonce_b64, &enc).unwrap()
+    );
+
+    // wrong key size, nonce size or key are rejected
+    assert!(
+        Secret::try_aes_gcm_encrypt(&BASE64.encode(&[0u8; 24]), &nonce_b64, text, b"").is_err()
+    );
+    assert!(Secret::try_aes_gcm_encrypt(&key256, &BASE64.encode(&[0u8; 24]), text, b"").is_err());
+    assert!(Secret::try_aes_gcm_decrypt(&key128, &nonce_b64, &enc, b"").is_err());
+}
+
+#[test]
+fn aes_gcm_siv_test() {
+    // RFC 8452 C.2: AES-256-GCM-SIV, empty plaintext
+    let mut key = [0u8; 32];
+    key[0] = 1;
+    let mut nonce = [0u8; 12];
+    nonce[0] = 3;
+    let key_b64 = BASE64.encode(&key);
+    let nonce_b64 = BASE64.encode(&nonce);
+
+    let enc = Secret::aes_gcm_siv_encrypt(&key_b64, &nonce_b64, "").unwrap();
+    let expected = data_encoding::HEXLOWER
+        .decode(b"07f5f4169bbf55a8400cd47ea6fd400f")
+        .unwrap();
+    assert_eq!(BASE64.encode(&expected), enc);
+
+    let text = "Hello AES-GCM-SIV!";
+    let enc = Secret::aes_gcm_siv_encrypt(&key_b64, &nonce_b64, text).unwrap();
+    assert_eq!(
+        text,
+        Secret::aes_gcm_siv_decrypt(&key_b64, &nonce_b64, &enc).unwrap()
+    );
+    assert!(aead_decrypt::<Aes256GcmSiv>(&key_b64, &BASE64.encode(&[0u8; 12]), &enc, b"").is_err());
+    assert!(
+        aead_encrypt::<Aes256GcmSiv>(&BASE64.encode(&[0u8; 16]), &nonce_b64, text, b"").is_err()
+    );
+}
+
+#[test]
+fn aead_aad_test() {
+    // RFC 8439 2.8.2: ChaCha20-Poly1305 with associated data
+    let key: Vec<u8> = (0x80u8..=0x9f).collect();
+    let nonce = [
+        0x07, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+    ];
+    let aad = data_encoding::HEXLOWER
+        .decode(b"50515253c0c1c2c3c4c5c6c7")
+        .unwrap();
+    let plaintext = "Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+    let key_b64 = BASE64.encode(&key);
+    let nonce_b64 = BASE64.encode(&nonce);
+
+    let enc = Secret::chacha_encrypt_aad(&key_b64, &nonce_b64, plaintext, &aad).unwrap();
+    let ct = BASE64.decode(enc.as_bytes()).unwrap();
+    assert_eq!(
+        "1ae10b594f09e26a7e902ecbd0600691",
+        data_encoding::HEXLOWER.encode(&ct[ct.len() - 16..])
+    );
+    assert_eq!(
+        plaintext,
+        Secret::chacha_decrypt_aad(&key_b64, &nonce_b64, &enc, &aad).unwrap()
+    );
+    assert!(aead_decrypt::<ChaCha20Poly1305>(&key_b64, &nonce_b64, &enc, b"other").is_err());
+    // empty AAD is the same as the plain API
+    assert_eq!(
+        Secret::chacha_encrypt(&key_b64, &nonce_b64, plaintext),
+        Some(Secret::chacha_encrypt_aad(&key_b64, &nonce_b64, plaintext, b"").unwrap())
+    );
+
+    let record = b"user:42/record:7";
+    let xnonce_b64 = BASE64.encode(&[9u8; 24]);
+    let enc = Secret::xchacha_encrypt_aad(&key_b64, &xnonce_b64, "note", record).unwrap();
+    assert_eq!(
+        "note",
+        Secret::xchacha_decrypt_aad(&key_b64, &xnonce_b64, &enc, record).unwrap()
+    );
+    assert!(
+        aead_decrypt::<XChaCha20Poly1305>(&key_b64, &xnonce_b64, &enc, b"user:43/record:7")
+            .is_err()
+    );
+    assert!(aead_decrypt::<XChaCha20Poly1305>(&key_b64, &xnonce_b64, &enc, b"").is_err());
+
+    let enc = Secret::aes_gcm_encrypt_aad(&key_b64, &nonce_b64, "note", record).unwrap();
+    assert_eq!(
+        "note",
+        Secret::aes_gcm_decrypt_aad(&key_b64, &nonce_b64, &enc, record).unwrap()
+    );
+    assert!(Secret::try_aes_gcm_decrypt(&key_b64, &nonce_b64, &enc, b"").is_err());
+
+    let enc = Secret::aes_gcm_siv_encrypt_aad(&key_b64, &nonce_b64, "note", record).unwrap();
+    assert_eq!(
+        "note",
+        Secret::aes_gcm_siv_decrypt_aad(&key_b64, &nonce_b64, &enc, record).unwrap()
+    );
+    assert!(aead_decrypt::<Aes256GcmSiv>(&key_b64, &nonce_b64, &enc, b"user").is_err());
+}
+
+#[test]
+fn aead_bytes_test() {
+    use data_encoding::HEXLOWER;
+
+    // NIST GCM test case 4: AES-128-GCM with associated data
+    let key = HEXLOWER
+        .decode(b"feffe9928665731c6d6a8f9467308308")
+        .unwrap();
+    let nonce = HEXLOWER.decode(b"cafebabefacedbaddecaf888").unwrap();
+    let plaintext = HEXLOWER
+        .decode(b"d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39")
+        .unwrap();
+    let aad = HEXLOWER
+        .decode(b"feedfacedeadbeeffeedfacedeadbeefabaddad2")
+        .unwrap();
+
+    let detached =
+        Secret::encrypt_detached(AeadAlgorithm::Aes128Gcm, &key, &nonce, &plaintext, &aad).unwrap();
+    assert_eq!(
+        "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+        HEXLOWER.encode(&detached.ciphertext())
+    );
+    assert_eq!(
+        "5bc94fbc3221a5db94fae95ae7121a47",
+        HEXLOWER.encode(&detached.tag())
+    );
+    assert_eq!(
+        plaintext,
+        Secret::decrypt_detached(
+            AeadAlgorithm::Aes128Gcm,
+            &key,
+            &nonce,
+            &detached.ciphertext(),
+            &detached.tag(),
+            &aad
+        )
+        .unwrap()
+    );
+
+    let sealed =
+        Secret::encrypt_bytes(AeadAlgorithm::Aes128Gcm, &key, &nonce, &plaintext, &aad).unwrap();
+    assert_eq!([detached.ciphertext(), detached.tag()].concat(), sealed);
+
+    // binary (non utf-8) data round-trips through every algorithm
+    let data: Vec<u8> = (0..=255u8).rev().collect();
+    for algorithm in [
+        AeadAlgorithm::ChaCha20Poly1305,
+        AeadAlgorithm::XChaCha20Poly1305,
+        AeadAlgorithm::Aes128Gcm,
+        AeadAlgorithm::Aes256Gcm,
+        AeadAlgorithm::Aes256GcmSiv,
+    ] {
+        let key = vec![7u8; algorithm.key_len()];
+        let nonce = vec![1u8; algorithm.nonce_len()];
+        let sealed = Secret::encrypt_bytes(algorithm, &key, &nonce, &data, b"").unwrap();
+        assert_eq!(data.len() + TAG_LEN, sealed.len());
+        assert_eq!(
+            data,
+            Secret::decrypt_bytes(algorithm, &key, &nonce, &sealed, b"").unwrap()
+        );
+
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 1;
+        assert!(algorithm.open(&key, &nonce, &tampered, b"").is_err());
+        assert!(algorithm.open(&key, &nonce, &sealed[..8], b"").is_err());
+        assert!(
+            algorithm
+                .open_detached(&key, &nonce, &sealed, &[0u8; 8], b"")
+                .is_err()
+        );
+        assert!(algorithm.seal(&key[1..], &nonce, &data, b"").is_err());
+        assert!(algorithm.seal(&key, &nonce[1..], &data, b"").is_err());
+    }
+
+    // the byte API matches the base64 string API
+    let key_b64 = BASE64.encode(&[0u8; 32]);
+    let nonce_b64 = BASE64.encode(&[0u8; 24]);
+    let enc = Secret::xchacha_encrypt(&key_b64, &nonce_b64, "bytes").unwrap();
+    let sealed = Secret::encrypt_bytes(
+        AeadAlgorithm::XChaCha20Poly1305,
+        &[0u8; 32],
+        &[0u8; 24],
+        b"bytes",
+        b"",
+    )
+    .unwrap();
+    assert_eq!(enc, BASE64.encode(&sealed));
+}
+
+#[test]
+fn envelope_test() {
+    let key = [42u8; 32];
+    let plaintext = b"envelope with \x00 binary \xff data";
+
+    for algorithm in [
+        AeadAlgorithm::ChaCha20Poly1305,
+        AeadAlgorithm::XChaCha20Poly1305,
+        AeadAlgorithm::Aes256Gcm,
+        AeadAlgorithm::Aes256GcmSiv,
+    ] {
+        let envelope = Secret::seal(algorithm, &key, "key-2024", plaintext).unwrap();
+        assert_eq!(b"RT\x01", &envelope[..3]);
+        assert_eq!(
+            3 + 1 + 1 + 8 + algorithm.nonce_len() + plaintext.len() + TAG_LEN,
+            envelope.len()
+        );
+        let header = Secret::envelope_header(&envelope).unwrap();
+        assert_eq!(algorithm, header.algorithm());
+        assert_eq!("key-2024", header.key_id());
+        assert_eq!(1, header.version());
+        assert_eq!(plaintext.to_vec(), Secret::open(&key, &envelope).unwrap());
+    }
+
+    // random nonce: sealing twice gives different envelopes
+    let a = Secret::seal_text(AeadAlgorithm::XChaCha20Poly1305, &key, "", plaintext).unwrap();
+    let b = Secret::seal_text(AeadAlgorithm::XChaCha20Poly1305, &key, "", plaintext).unwrap();
+    println!("envelope: {}", a);
+    assert_ne!(a, b);
+    assert!(!a.contains('=') && !a.contains('+') && !a.contains('/'));
+    assert_eq!(plaintext.to_vec(), Secret::open_text(&key, &a).unwrap());
+
+    // the header is authenticated
+    let envelope = Secret::seal(AeadAlgorithm::Aes128Gcm, &[1u8; 16], "k1", plaintext).unwrap();
+    let mut renamed = envelope.clone();
+    renamed[6] = b'2';
+    assert!(Secret::try_open(&[1u8; 16], &renamed).is_err());
+    assert!(Secret::try_open(&[2u8; 16], &envelope).is_err());
+    assert!(Secret::try_open(&[1u8; 16], &envelope[..20]).is_err());
+    let mut bad_magic = envelope.clone();
+    bad_magic[0] = b'X';
+    assert!(EnvelopeHeader::parse(&bad_magic).is_err());
+    let mut bad_version = envelope.clone();
+    bad_version[2] = 9;
+    assert!(EnvelopeHeader::parse(&bad_version).is_err());
+    assert!(
+        Secret::try_seal(
+            AeadAlgorithm::Aes128Gcm,
+            &[1u8; 16],
+            &"k".repeat(256),
+            plaintext
+        )
+        .is_err()
+    );
+    assert!(Secret::try_seal(AeadAlgorithm::Aes128Gcm, &[1u8; 32], "", plaintext).is_err());
+}
+
+#[test]
+fn passphrase_test() {
+    let params = KdfParams::argon2id(256, 1, 1).unwrap();
+    let note = "my encrypted note ✓";
+    let encoded = Secret::encrypt_with_passphrase("hunter2", note, &params).unwrap();
+    println!("passphrase: {}", encoded);
+    assert!(encoded.starts_with("rtpw$1$argon2id$m=256,t=1,p=1$"));
+    assert_eq!(
+        note,
+        Secret::decrypt_with_passphrase("hunter2", &encoded).unwrap()
+    );
+    assert_eq!(params, Secret::passphrase_params(&encoded).unwrap());
+    assert!(Secret::try_decrypt_with_passphrase("hunter3", &encoded).is_err());
+
+    // the recorded parameters are authenticated
+    let downgraded = encoded.replace("t=1", "t=2");
+    assert!(Secret::try_decrypt_with_passphrase("hunter2", &downgraded).is_err());
+
+    let params = KdfParams::scrypt(4, 8, 1).unwrap();
+    let encoded = Secret::encrypt_with_passphrase("hunter2", note, &params).unwrap();
+    assert!(encoded.starts_with("rtpw$1$scrypt$ln=4,r=8,p=1$"));
+    assert_eq!(
+        note,
+        Secret::decrypt_with_passphrase("hunter2", &encoded).unwrap()
+    );
+    assert_ne!(
+        encoded,
+        Secret::encrypt_with_passphrase("hunter2", note, &params).unwrap()
+    );
+
+    assert!(Secret::try_encrypt_with_passphrase("", note, &params).is_err());
+    assert!(Secret::try_decrypt_with_passphrase("hunter2", "rtpw$1$scrypt").is_err());
+
+    // scrypt ln=4, r=8 needs 16 KiB
+    assert_eq!(
+        note,
+        Secret::try_decrypt_with_passphrase_limit("hunter2", &encoded, 16).unwrap()
+    );
+    let err = Secret::try_decrypt_with_passphrase_limit("hunter2", &encoded, 15).unwrap_err();
+    assert!(err.contains("limit"));
+    let expensive = encoded.replacen("ln=4,r=8", "ln=24,r=32", 1);
+    assert!(Secret::try_decrypt_with_passphrase("hunter2", &expensive).is_err());
+
+    // without an explicit limit, settings above 256 MiB are refused before deriving
+    let expensive = encoded.replacen("ln=4,r=8", "ln=18,r=16", 1);
+    let err = Secret::try_decrypt_with_passphrase("hunter2", &expensive).unwrap_err();
+    assert!(err.contains("limit of 262144 KiB"));
+    assert!(
+        Secret::try_decrypt_with_passphrase("hunter2", &encoded.replacen("rtpw", "rtxx", 1))
+            .is_err()
+    );
+}
+
+#[test]
+fn aes_key_wrap_test() {
+    let b64 = |hex: &str| BASE64.encode(&data_encoding::HEXUPPER.decode(hex.as_bytes()).unwrap());
+    // RFC 3394 sections 4.1, 4.2, 4.3 and 4.6
+    for (kek, key, wrapped) in [
+        (
+            "000102030405060708090A0B0C0D0E0F",
+            "00112233445566778899AABBCCDDEEFF",
+            "1FA68B0A8112B447AEF34BD8FB5A7B829D3E862371D2CFE5",
+        ),
+        (
+            "000102030405060708090A0B0C0D0E0F1011121314151617",
+            "00112233445566778899AABBCCDDEEFF",
+            "96778B25AE6CA435F92B5B97C050AED2468AB8A17AD84E5D",
+        ),
+        (
+            "000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F",
+            "00112233445566778899AABBCCDDEEFF",
+            "64E8C3F9CE0F5BA263E9777905818A2A93C8191E7D6E8AE7",
+        ),
+        (
+            "000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F",
+            "00112233445566778899AABBCCDDEEFF000102030405060708090A0B0C0D0E0F",
+            "28C9F404C4B810F4CBCCB35CFB87F8263F5786E2D80ED326CBC7F0E71A99F43BFB988B9B7A02DD21",
+        ),
+    ] {
+        assert_eq!(
+            b64(wrapped),
+            Secret::aes_key_wrap(&b64(kek), &b64(key)).unwrap()
+        );
+        assert_eq!(
+            b64(key),
+            Secret::aes_key_unwrap(&b64(kek), &b64(wrapped)).unwrap()
+        );
+    }
+
+    // RFC 5649 section 6
+    let kek = b64("5840DF6E29B02AF1AB493B705BF16EA1AE8338F4DCC176A8");
+    for (key, wrapped) in [
+        (
+            "C37B7E6492584340BED12207808941155068F738",
+            "138BDEAA9B8FA7FC61F97742E72248EE5AE6AE5360D1AE6A5F54F373FA543B6A",
+        ),
+        ("466F7250617369", "AFBEB0F07DFBF5419200F2CCB50BB24F"),
+    ] {
+        assert_eq!(
+            b64(wrapped),
+            Secret::aes_key_wrap_pad(&kek, &b64(key)).unwrap()
+        );
+        assert_eq!(
+            b64(key),
+            Secret::aes_key_unwrap_pad(&kek, &b64(wrapped)).unwrap()
+        );
+    }
+
+    let kek = b64("000102030405060708090A0B0C0D0E0F");
+    let wrapped = b64("1FA68B0A8112B447AEF34BD8FB5A7B829D3E862371D2CFE5");
+    let mut tampered = BASE64.decode(wrapped.as_bytes()).unwrap();
+    tampered[5] ^= 1;
+    assert!(Secret::try_key_unwrap(&kek, &BASE64.encode(&tampered), false).is_err());
+    let other = b64("0F0E0D0C0B0A09080706050403020100");
+    assert!(Secret::try_key_unwrap(&other, &wrapped, false).is_err());
+    assert!(Secret::try_key_unwrap(&kek, &wrapped, true).is_err());
+    assert!(Secret::try_key_wrap(&kek, &b64("0011223344556677"), false).is_err());
+    assert!(Secret::try_key_wrap(&kek, &b64("00112233445566778899"), false).is_err());
+    assert!(Secret::try_key_wrap(&kek, "", true).is_err());
+    assert_eq!(
+        Err("error: key length must be 16, 24 or 32 bytes".to_string()),
+        Secret::try_key_wrap(&b64("0011"), &wrapped, false)
+    );
+}
+
+#[test]
+fn key_handle_secret_test() {
+    let handle = KeyHandle::try_generate(32, false).unwrap();
+    let envelope =
+        Secret::seal_with_handle(AeadAlgorithm::Aes256Gcm, &handle, "k1", b"handled").unwrap();
+    assert_eq!(
+        b"handled".to_vec(),
+        Secret::open_with_handle(&handle, &envelope).unwrap()
+    );
+
+    let key: Vec<u8> = (0..32).collect();
+    let handle = KeyHandle::try_import(&key, false).unwrap();
+    let nonce = [7u8; 12];
+    let sealed = Secret::encrypt_bytes_with_handle(
+        AeadAlgorithm::ChaCha20Poly1305,
+        &handle,
+        &nonce,
+        b"data",
+        b"aad",
+    )
+    .unwrap();
+    assert_eq!(
+        AeadAlgorithm::ChaCha20Poly1305
+            .seal(&key, &nonce, b"data", b"aad")
+            .unwrap(),
+        sealed
+    );
+    assert_eq!(
+        b"data".to_vec(),
+        Secret::decrypt_bytes_with_handle(
+            AeadAlgorithm::ChaCha20Poly1305,
+            &handle,
+            &nonce,
+            &sealed,
+            b"aad"
+        )
+        .unwrap()
+    );
+}
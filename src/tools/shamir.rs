@@ -0,0 +1,393 @@
+use crate::tools::parse::DataEncoding;
+use crate::tools::random::SafeRandom;
+use data_encoding::HEXLOWER;
+use sha2::{Digest, Sha256};
+use wasm_bindgen::prelude::*;
+
+const SHARE_VERSION: u8 = 1;
+const SET_ID_LEN: usize = 4;
+const CHECKSUM_LEN: usize = 4;
+const DIGEST_LEN: usize = 4;
+/// version || set id || threshold || index
+const SHARE_HEADER_LEN: usize = 1 + SET_ID_LEN + 1 + 1;
+const MAX_SECRET_LEN: usize = 1024;
+
+/// `gf_mul` multiply in GF(2^8) with the AES polynomial, without table lookups
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+/// `gf_inv` multiplicative inverse as a^254
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+fn checksum(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data)[..CHECKSUM_LEN].to_vec()
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// Text encoding of shares, via `DataEncoding`
+pub enum ShareEncoding {
+    /// lowercase hex
+    Hex = 0,
+    /// RFC 4648 base32 without padding, easy to read aloud and type
+    Base32 = 1,
+    /// base64url without padding
+    Base64Url = 2,
+    /// base62, alphanumeric only
+    Base62 = 3,
+}
+
+impl ShareEncoding {
+    fn encode(&self, data: &[u8]) -> String {
+        match self {
+            ShareEncoding::Hex => DataEncoding::encode_hex_bytes(data),
+            ShareEncoding::Base32 => DataEncoding::encode32_nopad_bytes(data),
+            ShareEncoding::Base64Url => DataEncoding::encode64_url_nopad_bytes(data),
+            ShareEncoding::Base62 => DataEncoding::encode62_bytes(data),
+        }
+    }
+
+    fn decode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        let data = match self {
+            ShareEncoding::Hex => DataEncoding::decode_hex_bytes(&text.to_ascii_lowercase()),
+            ShareEncoding::Base32 => DataEncoding::decode32_nopad_bytes(&text.to_ascii_uppercase()),
+            ShareEncoding::Base64Url => DataEncoding::decode64_url_nopad_bytes(&text),
+            ShareEncoding::Base62 => DataEncoding::decode62_bytes(&text).unwrap_or_default(),
+        };
+        if data.is_empty() {
+            return Err("error: share is not valid for this encoding".into());
+        }
+        Ok(data)
+    }
+}
+
+#[wasm_bindgen]
+/// `ShamirShare` metadata of one decoded share
+pub struct ShamirShare {
+    set_id: Vec<u8>,
+    threshold: u8,
+    index: u8,
+    payload: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl ShamirShare {
+    /// `try_decode` check version and checksum of a binary share
+    pub(crate) fn try_decode(data: &[u8]) -> Result<ShamirShare, String> {
+        if data.len() < SHARE_HEADER_LEN + DIGEST_LEN + 1 + CHECKSUM_LEN {
+            return Err("error: share is too short".into());
+        }
+        let (body, check) = data.split_at(data.len() - CHECKSUM_LEN);
+        if checksum(body) != check {
+            return Err("error: share checksum mismatch".into());
+        }
+        if body[0] != SHARE_VERSION {
+            return Err(format!("error: unsupported share version {}", body[0]));
+        }
+        let threshold = body[1 + SET_ID_LEN];
+        let index = body[2 + SET_ID_LEN];
+        if threshold == 0 || index == 0 {
+            return Err("error: invalid share header".into());
+        }
+        Ok(ShamirShare {
+            set_id: body[1..1 + SET_ID_LEN].to_vec(),
+            threshold,
+            index,
+            payload: body[SHARE_HEADER_LEN..].to_vec(),
+        })
+    }
+
+    /// `encode` version || set id || threshold || index || payload || checksum
+    fn encode(&self) -> Vec<u8> {
+        let mut out = vec![SHARE_VERSION];
+        out.extend_from_slice(&self.set_id);
+        out.push(self.threshold);
+        out.push(self.index);
+        out.extend_from_slice(&self.payload);
+        out.extend(checksum(&out));
+        out
+    }
+
+    /// `set_id` hex id shared by all shares of one split
+    pub fn set_id(&self) -> String {
+        HEXLOWER.encode(&self.set_id)
+    }
+
+    /// `threshold` number of shares needed to recover the secret
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// `index` share number, from 1
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// `secret_len` length of the shared secret in bytes
+    pub fn secret_len(&self) -> usize {
+        self.payload.len() - DIGEST_LEN
+    }
+}
+
+#[wasm_bindgen]
+/// `Shamir` Shamir secret sharing over GF(256)
+pub struct Shamir {}
+
+#[wasm_bindgen]
+impl Shamir {
+    /// `try_split` split a secret into `count` binary shares
+    pub(crate) fn try_split(
+        secret: &[u8],
+        threshold: u8,
+        count: u8,
+    ) -> Result<Vec<ShamirShare>, String> {
+        if secret.is_empty() || secret.len() > MAX_SECRET_LEN {
+            return Err(format!(
+                "error: secret must be between 1 and {} bytes",
+                MAX_SECRET_LEN
+            ));
+        }
+        if threshold == 0 || count == 0 || threshold > count {
+            return Err("error: threshold must be between 1 and the number of shares".into());
+        }
+
+        // the digest is shared along with the secret, so a wrong combination is detected
+        let mut data = secret.to_vec();
+        data.extend_from_slice(&Sha256::digest(secret)[..DIGEST_LEN]);
+        let set_id = SafeRandom::gen_bytes(SET_ID_LEN).raw();
+        let mut shares: Vec<ShamirShare> = (1..=count)
+            .map(|index| ShamirShare {
+                set_id: set_id.clone(),
+                threshold,
+                index,
+                payload: Vec::with_capacity(data.len()),
+            })
+            .collect();
+
+        for byte in data {
+            // random polynomial of degree threshold - 1 with the secret byte as constant term
+            let mut coefficients = vec![byte];
+            coefficients.extend(SafeRandom::gen_bytes(threshold as usize - 1).raw());
+            for share in shares.iter_mut() {
+                let y = coefficients
+                    .iter()
+                    .rev()
+                    .fold(0u8, |acc, c| gf_mul(acc, share.index) ^ c);
+                share.payload.push(y);
+            }
+        }
+        Ok(shares)
+    }
+
+    /// `try_combine` recover the secret from at least `threshold` matching shares
+    pub(crate) fn try_combine(shares: &[ShamirShare]) -> Result<Vec<u8>, String> {
+        let first = shares.first().ok_or("error: no shares given")?;
+        for share in shares {
+            if share.set_id != first.set_id
+                || share.threshold != first.threshold
+                || share.payload.len() != first.payload.len()
+            {
+                return Err("error: shares do not belong to the same secret".into());
+            }
+        }
+        let mut selected: Vec<&ShamirShare> = Vec::new();
+        for share in shares {
+            if selected.iter().any(|s| s.index == share.index) {
+                return Err(format!("error: share {} is given twice", share.index));
+            }
+            selected.push(share);
+        }
+        if selected.len() < first.threshold as usize {
+            return Err(format!(
+                "error: {} shares needed, {} given",
+                first.threshold,
+                selected.len()
+            ));
+        }
+        selected.truncate(first.threshold as usize);
+
+        // Lagrange interpolation at x = 0
+        let weights: Vec<u8> = selected
+            .iter()
+            .map(|share| {
+                selected
+                    .iter()
+                    .filter(|other| other.index != share.index)
+                    .fold(1u8, |acc, other| {
+                        gf_mul(acc, gf_mul(other.index, gf_inv(other.index ^ share.index)))
+                    })
+            })
+            .collect();
+        let data: Vec<u8> = (0..first.payload.len())
+            .map(|i| {
+                selected
+                    .iter()
+                    .zip(&weights)
+                    .fold(0u8, |acc, (share, w)| acc ^ gf_mul(share.payload[i], *w))
+            })
+            .collect();
+
+        let (secret, digest) = data.split_at(data.len() - DIGEST_LEN);
+        if Sha256::digest(secret)[..DIGEST_LEN] != *digest {
+            return Err("error: shares do not combine to a valid secret".into());
+        }
+        Ok(secret.to_vec())
+    }
+
+    /// `split` split a secret into text shares
+    ///
+    /// ### Arguments
+    /// * `secret` - Bytes to protect, up to 1024
+    /// * `threshold` - Shares needed to recover the secret
+    /// * `count` - Shares to create, up to 255
+    /// * `encoding` - Text encoding of each share
+    ///
+    /// ### Returns
+    /// Shares carrying a set id, the threshold, their index and a checksum
+    pub fn split(
+        secret: &[u8],
+        threshold: u8,
+        count: u8,
+        encoding: ShareEncoding,
+    ) -> Result<Vec<String>, JsValue> {
+        Self::try_split(secret, threshold, count)
+            .map(|shares| {
+                shares
+                    .iter()
+                    .map(|share| encoding.encode(&share.encode()))
+                    .collect()
+            })
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `try_combine_text` decode and combine text shares
+    pub(crate) fn try_combine_text(
+        shares: &[String],
+        encoding: ShareEncoding,
+    ) -> Result<Vec<u8>, String> {
+        let shares = shares
+            .iter()
+            .map(|share| ShamirShare::try_decode(&encoding.decode(share)?))
+            .collect::<Result<Vec<ShamirShare>, String>>()?;
+        Self::try_combine(&shares)
+    }
+
+    /// `combine` recover the secret from text shares; fails on corrupted or mismatched shares
+    pub fn combine(shares: Vec<String>, encoding: ShareEncoding) -> Result<Vec<u8>, JsValue> {
+        Self::try_combine_text(&shares, encoding).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `inspect` read the metadata of a text share
+    pub fn inspect(share: &str, encoding: ShareEncoding) -> Result<ShamirShare, JsValue> {
+        encoding
+            .decode(share)
+            .and_then(|data| ShamirShare::try_decode(&data))
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[test]
+fn shamir_gf256_test() {
+    // FIPS 197 section 4.2 example: {57} x {83} = {c1}
+    assert_eq!(0xc1, gf_mul(0x57, 0x83));
+    assert_eq!(0xfe, gf_mul(0x57, 0x13));
+    for a in 1..=255u8 {
+        assert_eq!(1, gf_mul(a, gf_inv(a)));
+    }
+}
+
+#[test]
+fn shamir_split_combine_test() {
+    let secret = b"correct horse battery staple";
+    for encoding in [
+        ShareEncoding::Hex,
+        ShareEncoding::Base32,
+        ShareEncoding::Base64Url,
+        ShareEncoding::Base62,
+    ] {
+        let shares = Shamir::split(secret, 3, 5, encoding).unwrap();
+        println!("{:?}: {:?}", encoding, shares);
+        assert_eq!(5, shares.len());
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let picked: Vec<String> = subset.iter().map(|i| shares[*i].clone()).collect();
+            assert_eq!(
+                secret.to_vec(),
+                Shamir::try_combine_text(&picked, encoding).unwrap()
+            );
+        }
+        assert_eq!(
+            secret.to_vec(),
+            Shamir::try_combine_text(&shares, encoding).unwrap()
+        );
+        assert!(Shamir::try_combine_text(&shares[..2], encoding).is_err());
+    }
+
+    let shares = Shamir::try_split(&[0x42], 1, 3).unwrap();
+    assert_eq!(vec![0x42], Shamir::try_combine(&shares[2..]).unwrap());
+    assert!(Shamir::try_split(b"", 2, 3).is_err());
+    assert!(Shamir::try_split(b"x", 4, 3).is_err());
+    assert!(Shamir::try_split(b"x", 0, 3).is_err());
+    assert!(Shamir::try_split(&[0u8; 2000], 2, 3).is_err());
+}
+
+#[test]
+fn shamir_mismatch_test() {
+    let encoding = ShareEncoding::Base32;
+    let a = Shamir::split(b"first secret", 2, 3, encoding).unwrap();
+    let b = Shamir::split(b"other secret", 2, 3, encoding).unwrap();
+
+    let info = ShamirShare::try_decode(&encoding.decode(&a[1]).unwrap()).unwrap();
+    assert_eq!(2, info.threshold());
+    assert_eq!(2, info.index());
+    assert_eq!(12, info.secret_len());
+    assert_eq!(8, info.set_id().len());
+
+    // lowercase and spaces are tolerated when typing shares back in
+    let typed = a[0].to_lowercase().replace("", " ");
+    assert_eq!(
+        b"first secret".to_vec(),
+        Shamir::try_combine_text(&[typed, a[2].clone()], encoding).unwrap()
+    );
+
+    let err = Shamir::try_combine_text(&[a[0].clone(), b[1].clone()], encoding).unwrap_err();
+    assert_eq!("error: shares do not belong to the same secret", err);
+    let err = Shamir::try_combine_text(&[a[0].clone(), a[0].clone()], encoding).unwrap_err();
+    assert_eq!("error: share 1 is given twice", err);
+    assert!(Shamir::try_combine_text(&[], encoding).is_err());
+
+    // a flipped byte fails the share checksum
+    let mut raw = encoding.decode(&a[0]).unwrap();
+    raw[10] ^= 1;
+    let corrupted = encoding.encode(&raw);
+    let err = Shamir::try_combine_text(&[corrupted, a[1].clone()], encoding).unwrap_err();
+    assert_eq!("error: share checksum mismatch", err);
+
+    // a forged share with a valid checksum still fails the secret digest
+    let mut forged = ShamirShare::try_decode(&encoding.decode(&a[1]).unwrap()).unwrap();
+    forged.payload[0] ^= 1;
+    let first = ShamirShare::try_decode(&encoding.decode(&a[0]).unwrap()).unwrap();
+    let err = Shamir::try_combine(&[first, forged]).unwrap_err();
+    assert_eq!("error: shares do not combine to a valid secret", err);
+    assert!(encoding.decode("not base32!").is_err());
+}
@@ -0,0 +1,336 @@
+use crate::tools::hash::Hash;
+use crate::tools::random::SafeRandom;
+use crate::tools::secret::AeadAlgorithm;
+use wasm_bindgen::prelude::*;
+
+const STREAM_MAGIC: &[u8; 4] = b"RTS1";
+const STREAM_INFO: &[u8] = b"rtools stream payload key";
+const STREAM_ALGORITHM: AeadAlgorithm = AeadAlgorithm::ChaCha20Poly1305;
+const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = STREAM_MAGIC.len() + 4 + SALT_LEN;
+const TAG_LEN: usize = 16;
+const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+/// `payload_key` per-stream key, so counter nonces never repeat under the caller's key
+fn payload_key(key: &[u8], salt: &[u8]) -> Result<Vec<u8>, String> {
+    if key.len() != STREAM_ALGORITHM.key_len() {
+        return Err(format!(
+            "error: key length must be {} bytes",
+            STREAM_ALGORITHM.key_len()
+        ));
+    }
+    Hash::gen_hkdf(key, salt, STREAM_INFO, STREAM_ALGORITHM.key_len())
+        .ok_or_else(|| "error: key derivation failed".into())
+}
+
+/// `chunk_nonce` 11-byte big-endian counter || last-chunk flag (STREAM, as in age)
+fn chunk_nonce(counter: u64, last: bool) -> Vec<u8> {
+    let mut nonce = vec![0u8; 3];
+    nonce.extend_from_slice(&counter.to_be_bytes());
+    nonce.push(last as u8);
+    nonce
+}
+
+fn check_chunk_size(chunk_size: u32) -> Result<(), String> {
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return Err(format!(
+            "error: chunk size must be between 1 and {} bytes",
+            MAX_CHUNK_SIZE
+        ));
+    }
+    Ok(())
+}
+
+#[wasm_bindgen]
+/// `EncryptStream` chunked ChaCha20-Poly1305 encryption (STREAM construction)
+pub struct EncryptStream {
+    key: Vec<u8>,
+    header: Vec<u8>,
+    chunk_size: usize,
+    counter: u64,
+    buffer: Vec<u8>,
+    finished: bool,
+}
+
+#[wasm_bindgen]
+impl EncryptStream {
+    /// `try_new` start a stream with a random salt
+    pub(crate) fn try_new(key: &[u8], chunk_size: u32) -> Result<EncryptStream, String> {
+        check_chunk_size(chunk_size)?;
+        let salt = SafeRandom::gen_bytes(SALT_LEN).raw();
+        let mut header = STREAM_MAGIC.to_vec();
+        header.extend_from_slice(&chunk_size.to_be_bytes());
+        header.extend_from_slice(&salt);
+        let mut stream = Self::with_payload_key(payload_key(key, &salt)?, chunk_size as usize);
+        stream.header = header;
+        Ok(stream)
+    }
+
+    /// `with_payload_key` headerless stream over an already derived key (used by age)
+    pub(crate) fn with_payload_key(key: Vec<u8>, chunk_size: usize) -> EncryptStream {
+        EncryptStream {
+            key,
+            header: Vec::new(),
+            chunk_size,
+            counter: 0,
+            buffer: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// `seal_chunk` encrypt the next chunk and advance the counter
+    fn seal_chunk(&mut self, chunk: &[u8], last: bool) -> Result<Vec<u8>, String> {
+        let nonce = chunk_nonce(self.counter, last);
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or("error: stream is too long")?;
+        STREAM_ALGORITHM.seal(&self.key, &nonce, chunk, b"")
+    }
+
+    /// `try_update` buffer plaintext and seal every complete chunk except the last one
+    pub(crate) fn try_update(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if self.finished {
+            return Err("error: stream is already finished".into());
+        }
+        self.buffer.extend_from_slice(data);
+        let mut out = Vec::new();
+        // keep at least one chunk back: only `finalize` knows which chunk is last
+        while self.buffer.len() > self.chunk_size {
+            let chunk: Vec<u8> = self.buffer.drain(..self.chunk_size).collect();
+            out.extend(self.seal_chunk(&chunk, false)?);
+        }
+        Ok(out)
+    }
+
+    /// `try_finalize` seal the remaining plaintext as the final chunk
+    pub(crate) fn try_finalize(&mut self) -> Result<Vec<u8>, String> {
+        if self.finished {
+            return Err("error: stream is already finished".into());
+        }
+        self.finished = true;
+        let chunk = std::mem::take(&mut self.buffer);
+        self.seal_chunk(&chunk, true)
+    }
+
+    /// `new` start an encryption stream
+    ///
+    /// ### Arguments
+    /// * `key` - 32-byte key
+    /// * `chunk_size` - Plaintext bytes per chunk (1 to 16 MiB)
+    pub fn new(key: &[u8], chunk_size: u32) -> Result<EncryptStream, JsValue> {
+        Self::try_new(key, chunk_size).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `with_default_chunk_size` start an encryption stream with 64 KiB chunks
+    pub fn with_default_chunk_size(key: &[u8]) -> Result<EncryptStream, JsValue> {
+        Self::new(key, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// `header` stream header to write before the first chunk: magic, chunk size and salt
+    pub fn header(&self) -> Vec<u8> {
+        self.header.clone()
+    }
+
+    /// `update` encrypt more plaintext; returns zero or more sealed chunks
+    pub fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.try_update(data).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `finalize` seal the final chunk; the stream cannot be used afterwards
+    pub fn finalize(&mut self) -> Result<Vec<u8>, JsValue> {
+        self.try_finalize().map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[wasm_bindgen]
+/// `DecryptStream` chunked decryption of an `EncryptStream` output
+pub struct DecryptStream {
+    key: Vec<u8>,
+    sealed_size: usize,
+    counter: u64,
+    buffer: Vec<u8>,
+    finished: bool,
+}
+
+#[wasm_bindgen]
+impl DecryptStream {
+    /// `try_new` read the stream header
+    pub(crate) fn try_new(key: &[u8], header: &[u8]) -> Result<DecryptStream, String> {
+        if header.len() != HEADER_LEN || &header[..STREAM_MAGIC.len()] != STREAM_MAGIC {
+            return Err("error: invalid stream header".into());
+        }
+        let size_bytes: [u8; 4] = header[4..8].try_into().expect("header length is checked");
+        let chunk_size = u32::from_be_bytes(size_bytes);
+        check_chunk_size(chunk_size)?;
+        Ok(Self::with_payload_key(
+            payload_key(key, &header[8..])?,
+            chunk_size as usize,
+        ))
+    }
+
+    /// `with_payload_key` headerless stream over an already derived key (used by age)
+    pub(crate) fn with_payload_key(key: Vec<u8>, chunk_size: usize) -> DecryptStream {
+        DecryptStream {
+            key,
+            sealed_size: chunk_size + TAG_LEN,
+            counter: 0,
+            buffer: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// `open_chunk` decrypt the next chunk; fails on reordering, truncation or tampering
+    fn open_chunk(&mut self, sealed: &[u8], last: bool) -> Result<Vec<u8>, String> {
+        let nonce = chunk_nonce(self.counter, last);
+        let chunk = STREAM_ALGORITHM
+            .open(&self.key, &nonce, sealed, b"")
+            .map_err(|_| format!("error: chunk {} failed authentication", self.counter))?;
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or("error: stream is too long")?;
+        Ok(chunk)
+    }
+
+    /// `try_update` buffer ciphertext and open every complete chunk except the last one
+    pub(crate) fn try_update(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if self.finished {
+            return Err("error: stream is already finished".into());
+        }
+        self.buffer.extend_from_slice(data);
+        let mut out = Vec::new();
+        while self.buffer.len() > self.sealed_size {
+            let sealed: Vec<u8> = self.buffer.drain(..self.sealed_size).collect();
+            out.extend(self.open_chunk(&sealed, false)?);
+        }
+        Ok(out)
+    }
+
+    /// `try_finalize` open the remaining ciphertext as the final chunk
+    pub(crate) fn try_finalize(&mut self) -> Result<Vec<u8>, String> {
+        if self.finished {
+            return Err("error: stream is already finished".into());
+        }
+        self.finished = true;
+        let sealed = std::mem::take(&mut self.buffer);
+        if sealed.len() == TAG_LEN && self.counter > 0 {
+            return Err("error: empty final chunk".into());
+        }
+        self.open_chunk(&sealed, true)
+            .map_err(|_| "error: stream is truncated or the final chunk is corrupted".into())
+    }
+
+    /// `new` start a decryption stream from the header written by `EncryptStream`
+    pub fn new(key: &[u8], header: &[u8]) -> Result<DecryptStream, JsValue> {
+        Self::try_new(key, header).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `header_len` number of header bytes to read before creating the stream
+    pub fn header_len() -> usize {
+        HEADER_LEN
+    }
+
+    /// `update` decrypt more ciphertext; returns zero or more plaintext chunks
+    pub fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.try_update(data).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `finalize` verify the final chunk; only then is the plaintext known to be complete
+    pub fn finalize(&mut self) -> Result<Vec<u8>, JsValue> {
+        self.try_finalize().map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+#[cfg(test)]
+fn stream_encrypt(key: &[u8], chunk_size: u32, data: &[u8], feed: usize) -> Vec<u8> {
+    let mut enc = EncryptStream::new(key, chunk_size).unwrap();
+    let mut out = enc.header();
+    for piece in data.chunks(feed.max(1)) {
+        out.extend(enc.update(piece).unwrap());
+    }
+    out.extend(enc.finalize().unwrap());
+    out
+}
+
+#[cfg(test)]
+fn stream_decrypt(key: &[u8], sealed: &[u8], feed: usize) -> Result<Vec<u8>, String> {
+    let (header, body) = sealed.split_at(HEADER_LEN);
+    let mut dec = DecryptStream::try_new(key, header)?;
+    let mut out = Vec::new();
+    for piece in body.chunks(feed.max(1)) {
+        out.extend(dec.try_update(piece)?);
+    }
+    out.extend(dec.try_finalize()?);
+    Ok(out)
+}
+
+#[test]
+fn stream_roundtrip_test() {
+    let key = [5u8; 32];
+    let data: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+
+    for (chunk_size, len) in [
+        (64, 0),
+        (64, 1),
+        (64, 64),
+        (64, 128),
+        (64, 1000),
+        (100, 1000),
+    ] {
+        let plaintext = &data[..len];
+        let sealed = stream_encrypt(&key, chunk_size, plaintext, 37);
+        let chunks = len.div_ceil(chunk_size as usize).max(1);
+        assert_eq!(HEADER_LEN + len + chunks * TAG_LEN, sealed.len());
+        assert_eq!(plaintext, stream_decrypt(&key, &sealed, 53).unwrap());
+        assert_eq!(plaintext, stream_decrypt(&key, &sealed, 1).unwrap());
+    }
+
+    let stream = EncryptStream::with_default_chunk_size(&key).unwrap();
+    assert_eq!(&DEFAULT_CHUNK_SIZE.to_be_bytes(), &stream.header()[4..8]);
+    assert_eq!(HEADER_LEN, DecryptStream::header_len());
+}
+
+#[test]
+fn stream_tamper_test() {
+    let key = [5u8; 32];
+    let data = vec![9u8; 300];
+    let sealed = stream_encrypt(&key, 64, &data, 300);
+    let sealed_size = 64 + TAG_LEN;
+    let body = &sealed[HEADER_LEN..];
+
+    // truncated at a chunk boundary: the new last chunk was not sealed as final
+    let truncated = &sealed[..HEADER_LEN + 2 * sealed_size];
+    assert!(stream_decrypt(&key, truncated, 1000).is_err());
+
+    // reordered chunks
+    let mut reordered = sealed[..HEADER_LEN].to_vec();
+    reordered.extend_from_slice(&body[sealed_size..2 * sealed_size]);
+    reordered.extend_from_slice(&body[..sealed_size]);
+    reordered.extend_from_slice(&body[2 * sealed_size..]);
+    assert!(stream_decrypt(&key, &reordered, 1000).is_err());
+
+    // appended data after the final chunk
+    let mut extended = sealed.clone();
+    extended.extend_from_slice(&[0u8; 20]);
+    assert!(stream_decrypt(&key, &extended, 1000).is_err());
+
+    let mut flipped = sealed.clone();
+    flipped[HEADER_LEN + 10] ^= 1;
+    assert!(stream_decrypt(&key, &flipped, 1000).is_err());
+
+    // the salt in the header selects the payload key
+    let mut salted = sealed.clone();
+    salted[HEADER_LEN - 1] ^= 1;
+    assert!(stream_decrypt(&key, &salted, 1000).is_err());
+    assert!(stream_decrypt(&[6u8; 32], &sealed, 1000).is_err());
+
+    let mut enc = EncryptStream::try_new(&key, 64).unwrap();
+    enc.try_finalize().unwrap();
+    assert!(enc.try_update(b"late").is_err());
+    assert!(enc.try_finalize().is_err());
+    assert!(EncryptStream::try_new(&key, 0).is_err());
+    assert!(EncryptStream::try_new(&key[..16], 64).is_err());
+    assert!(DecryptStream::try_new(&key, b"RTS1").is_err());
+}
@@ -0,0 +1,610 @@
+use crate::tools::kdf::{DEFAULT_MAX_MEMORY_KIB, KdfAlgorithm, KdfParams};
+use crate::tools::keyhandle::KeyHandle;
+use crate::tools::random::SafeRandom;
+use crate::tools::secret::AeadAlgorithm;
+use data_encoding::BASE64URL_NOPAD;
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+const VAULT_PREFIX: &str = "rtvault$1";
+const VAULT_ALGORITHM: AeadAlgorithm = AeadAlgorithm::XChaCha20Poly1305;
+const SALT_LEN: usize = 16;
+const MAX_NAME_LEN: usize = 255;
+// the vault guards every stored secret, so wrapping requires at least the OWASP Argon2id baseline
+const MIN_MEMORY_KIB: u64 = 19 * 1024;
+const MIN_ARGON2_PASSES: u32 = 2;
+
+/// `VaultEntry` one named secret with its metadata
+struct VaultEntry {
+    name: String,
+    kind: String,
+    created: u64,
+    updated: u64,
+    value: Zeroizing<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+/// `VaultEntryInfo` metadata of an entry, without its value
+pub struct VaultEntryInfo {
+    name: String,
+    kind: String,
+    created: u64,
+    updated: u64,
+    len: usize,
+}
+
+#[wasm_bindgen]
+impl VaultEntryInfo {
+    /// `name` unique entry name
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// `kind` caller-defined type label, e.g. `totp`, `token`, `ed25519`
+    pub fn kind(&self) -> String {
+        self.kind.clone()
+    }
+
+    /// `created` timestamp passed when the entry was added
+    pub fn created(&self) -> u64 {
+        self.created
+    }
+
+    /// `updated` timestamp of the last change to the value or name
+    pub fn updated(&self) -> u64 {
+        self.updated
+    }
+
+    /// `len` value length in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// `is_empty` whether the value is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+fn write_field(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+/// `BodyReader` reads the length-prefixed fields written by `write_field`
+struct BodyReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BodyReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or("error: vault body is truncated")?;
+        let out = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(
+            self.take(4)?.try_into().expect("4 bytes"),
+        ))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(
+            self.take(8)?.try_into().expect("8 bytes"),
+        ))
+    }
+
+    fn field(&mut self) -> Result<&[u8], String> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn text(&mut self) -> Result<String, String> {
+        String::from_utf8(self.field()?.to_vec()).map_err(|_| "error: invalid vault entry".into())
+    }
+}
+
+fn decode_field(field: &str, name: &str) -> Result<Vec<u8>, String> {
+    BASE64URL_NOPAD
+        .decode(field.as_bytes())
+        .map_err(|_| format!("error: invalid base64url {}", name))
+}
+
+/// `check_strength` refuse KDF settings too cheap to protect the data key
+fn check_strength(params: &KdfParams) -> Result<(), String> {
+    if params.memory_kib() < MIN_MEMORY_KIB
+        || (params.algorithm() == KdfAlgorithm::Argon2id && params.t_cost() < MIN_ARGON2_PASSES)
+    {
+        return Err(format!(
+            "error: vault kdf needs at least {} KiB of memory (and t >= {} for Argon2id), e.g. KdfParams::recommended()",
+            MIN_MEMORY_KIB, MIN_ARGON2_PASSES
+        ));
+    }
+    Ok(())
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!(
+            "error: entry name must be between 1 and {} bytes",
+            MAX_NAME_LEN
+        ));
+    }
+    Ok(())
+}
+
+#[wasm_bindgen]
+/// `Vault` passphrase-protected keystore of named secrets
+///
+/// Entries are encrypted with a random data key; the passphrase only wraps that key,
+/// so changing it does not touch the entries.
+pub struct Vault {
+    data_key: KeyHandle,
+    params: KdfParams,
+    salt: Vec<u8>,
+    wrapped_key: Vec<u8>,
+    entries: Vec<VaultEntry>,
+}
+
+#[wasm_bindgen]
+impl Vault {
+    /// `key_header` `rtvault$1$<kdf>$<params>$<salt>`, authenticated when wrapping the data key
+    fn key_header(params: &KdfParams, salt: &[u8]) -> String {
+        format!(
+            "{}${}${}",
+            VAULT_PREFIX,
+            params.encode(),
+            BASE64URL_NOPAD.encode(salt)
+        )
+    }
+
+    /// `wrap_data_key` derive a key from the passphrase and seal the data key with it
+    ///
+    /// The vault is only updated once both steps succeed, so a failure keeps the old passphrase.
+    fn wrap_data_key(&mut self, passphrase: &str, params: &KdfParams) -> Result<(), String> {
+        if passphrase.is_empty() {
+            return Err("error: passphrase is required".into());
+        }
+        check_strength(params)?;
+        let salt = SafeRandom::gen_bytes(SALT_LEN).raw();
+        let kek = Zeroizing::new(params.derive(
+            passphrase.as_bytes(),
+            &salt,
+            VAULT_ALGORITHM.key_len(),
+        )?);
+        let nonce = SafeRandom::gen_bytes(VAULT_ALGORITHM.nonce_len()).raw();
+        let sealed = VAULT_ALGORITHM.seal(
+            &kek,
+            &nonce,
+            self.data_key.bytes(),
+            Self::key_header(params, &salt).as_bytes(),
+        )?;
+        self.params = *params;
+        self.salt = salt;
+        self.wrapped_key = [nonce, sealed].concat();
+        Ok(())
+    }
+
+    fn find(&self, name: &str) -> Result<&VaultEntry, String> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| format!("error: no entry named {}", name))
+    }
+
+    fn find_mut(&mut self, name: &str) -> Result<&mut VaultEntry, String> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| format!("error: no entry named {}", name))
+    }
+
+    /// `try_create` empty vault with a fresh data key
+    pub(crate) fn try_create(passphrase: &str, params: &KdfParams) -> Result<Vault, String> {
+        let mut vault = Vault {
+            data_key: KeyHandle::try_generate(VAULT_ALGORITHM.key_len(), false)?,
+            params: *params,
+            salt: Vec::new(),
+            wrapped_key: Vec::new(),
+            entries: Vec::new(),
+        };
+        vault.wrap_data_key(passphrase, params)?;
+        Ok(vault)
+    }
+
+    /// `try_unlock` parse a blob, unwrap the data key and decrypt the entries
+    pub(crate) fn try_unlock(blob: &str, passphrase: &str) -> Result<Vault, String> {
+        Self::try_unlock_with_limit(blob, passphrase, DEFAULT_MAX_MEMORY_KIB)
+    }
+
+    /// `try_unlock_with_limit` as `try_unlock`, refusing KDF settings above `max_memory_kib` before deriving
+    pub(crate) fn try_unlock_with_limit(
+        blob: &str,
+        passphrase: &str,
+        max_memory_kib: u64,
+    ) -> Result<Vault, String> {
+        let fields: Vec<&str> = blob.trim().split('$').collect();
+        if fields.len() != 8 || format!("{}${}", fields[0], fields[1]) != VAULT_PREFIX {
+            return Err("error: not a vault".into());
+        }
+        let params = KdfParams::parse(fields[2], fields[3])?;
+        params.check_memory(max_memory_kib)?;
+        let salt = decode_field(fields[4], "salt")?;
+        let wrapped_key = decode_field(fields[5], "key")?;
+        let nonce = decode_field(fields[6], "nonce")?;
+        let sealed = decode_field(fields[7], "body")?;
+
+        let nonce_len = VAULT_ALGORITHM.nonce_len();
+        if wrapped_key.len() <= nonce_len {
+            return Err("error: invalid vault key".into());
+        }
+        let key_header = fields[..5].join("$");
+        let kek = Zeroizing::new(params.derive(
+            passphrase.as_bytes(),
+            &salt,
+            VAULT_ALGORITHM.key_len(),
+        )?);
+        let data_key = Zeroizing::new(
+            VAULT_ALGORITHM
+                .open(
+                    &kek,
+                    &wrapped_key[..nonce_len],
+                    &wrapped_key[nonce_len..],
+                    key_header.as_bytes(),
+                )
+                .map_err(|_| "error: wrong passphrase or corrupted vault")?,
+        );
+        let data_key = KeyHandle::try_import(&data_key, false)?;
+
+        let body_header = fields[..6].join("$");
+        let body = Zeroizing::new(
+            VAULT_ALGORITHM
+                .open(data_key.bytes(), &nonce, &sealed, body_header.as_bytes())
+                .map_err(|_| "error: vault contents are corrupted")?,
+        );
+        let mut reader = BodyReader {
+            data: &body,
+            pos: 0,
+        };
+        let count = reader.u32()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            entries.push(VaultEntry {
+                name: reader.text()?,
+                kind: reader.text()?,
+                created: reader.u64()?,
+                updated: reader.u64()?,
+                value: Zeroizing::new(reader.field()?.to_vec()),
+            });
+        }
+        if reader.pos != body.len() {
+            return Err("error: vault contents are corrupted".into());
+        }
+        Ok(Vault {
+            data_key,
+            params,
+            salt,
+            wrapped_key,
+            entries,
+        })
+    }
+
+    /// `try_export` seal all entries into one blob
+    pub(crate) fn try_export(&self) -> Result<String, String> {
+        let mut body = Zeroizing::new(Vec::new());
+        body.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        for entry in &self.entries {
+            write_field(&mut body, entry.name.as_bytes());
+            write_field(&mut body, entry.kind.as_bytes());
+            body.extend_from_slice(&entry.created.to_be_bytes());
+            body.extend_from_slice(&entry.updated.to_be_bytes());
+            write_field(&mut body, &entry.value);
+        }
+        // the wrapped key is part of the associated data, so header and body cannot be mixed
+        let header = format!(
+            "{}${}",
+            Self::key_header(&self.params, &self.salt),
+            BASE64URL_NOPAD.encode(&self.wrapped_key)
+        );
+        let nonce = SafeRandom::gen_bytes(VAULT_ALGORITHM.nonce_len()).raw();
+        let sealed =
+            VAULT_ALGORITHM.seal(self.data_key.bytes(), &nonce, &body, header.as_bytes())?;
+        Ok(format!(
+            "{}${}${}",
+            header,
+            BASE64URL_NOPAD.encode(&nonce),
+            BASE64URL_NOPAD.encode(&sealed)
+        ))
+    }
+
+    /// `try_add` insert a new entry
+    pub(crate) fn try_add(
+        &mut self,
+        name: &str,
+        kind: &str,
+        value: &[u8],
+        timestamp: u64,
+    ) -> Result<(), String> {
+        check_name(name)?;
+        if self.find(name).is_ok() {
+            return Err(format!("error: entry {} already exists", name));
+        }
+        self.entries.push(VaultEntry {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            created: timestamp,
+            updated: timestamp,
+            value: Zeroizing::new(value.to_vec()),
+        });
+        Ok(())
+    }
+
+    /// `try_rename` give an entry a new unique name
+    pub(crate) fn try_rename(
+        &mut self,
+        name: &str,
+        new_name: &str,
+        timestamp: u64,
+    ) -> Result<(), String> {
+        check_name(new_name)?;
+        if name != new_name && self.find(new_name).is_ok() {
+            return Err(format!("error: entry {} already exists", new_name));
+        }
+        let entry = self.find_mut(name)?;
+        entry.name = new_name.to_string();
+        entry.updated = timestamp;
+        Ok(())
+    }
+
+    /// `create` new empty vault
+    ///
+    /// ### Arguments
+    /// * `passphrase` - Master passphrase
+    /// * `params` - KDF settings, at least `KdfParams::recommended()` (19 MiB, and t >= 2 for Argon2id)
+    pub fn create(passphrase: &str, params: &KdfParams) -> Result<Vault, JsValue> {
+        Self::try_create(passphrase, params).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `unlock` open a blob written by `export`; fails on a wrong passphrase or any modification
+    ///
+    /// Blobs whose KDF needs more than 256 MiB are refused; use `unlock_with_limit` for those.
+    pub fn unlock(blob: &str, passphrase: &str) -> Result<Vault, JsValue> {
+        Self::try_unlock(blob, passphrase).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `unlock_with_limit` as `unlock`, with the KDF memory budget in KiB chosen by the caller
+    pub fn unlock_with_limit(
+        blob: &str,
+        passphrase: &str,
+        max_memory_kib: u64,
+    ) -> Result<Vault, JsValue> {
+        Self::try_unlock_with_limit(blob, passphrase, max_memory_kib)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `export` serialize the vault to one encrypted, authenticated string
+    pub fn export(&self) -> Result<String, JsValue> {
+        self.try_export().map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `add` store a new secret
+    ///
+    /// ### Arguments
+    /// * `name` - Unique name (1 to 255 bytes)
+    /// * `kind` - Type label kept as metadata
+    /// * `value` - Secret bytes
+    /// * `timestamp` - Creation time, e.g. unix seconds
+    pub fn add(
+        &mut self,
+        name: &str,
+        kind: &str,
+        value: &[u8],
+        timestamp: u64,
+    ) -> Result<(), JsValue> {
+        self.try_add(name, kind, value, timestamp)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `update` replace the value of an existing entry
+    pub fn update(&mut self, name: &str, value: &[u8], timestamp: u64) -> Result<(), JsValue> {
+        let entry = self.find_mut(name).map_err(|e| JsValue::from_str(&e))?;
+        entry.value = Zeroizing::new(value.to_vec());
+        entry.updated = timestamp;
+        Ok(())
+    }
+
+    /// `get` value of an entry
+    pub fn get(&self, name: &str) -> Result<Vec<u8>, JsValue> {
+        self.find(name)
+            .map(|entry| entry.value.to_vec())
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `entry` metadata of an entry
+    pub fn entry(&self, name: &str) -> Result<VaultEntryInfo, JsValue> {
+        self.find(name)
+            .map(|entry| VaultEntryInfo {
+                name: entry.name.clone(),
+                kind: entry.kind.clone(),
+                created: entry.created,
+                updated: entry.updated,
+                len: entry.value.len(),
+            })
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `names` entry names in insertion order
+    pub fn names(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    /// `contains` whether an entry exists
+    pub fn contains(&self, name: &str) -> bool {
+        self.find(name).is_ok()
+    }
+
+    /// `remove` delete an entry; returns whether it existed
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.name != name);
+        self.entries.len() != before
+    }
+
+    /// `rename` change an entry's name
+    pub fn rename(&mut self, name: &str, new_name: &str, timestamp: u64) -> Result<(), JsValue> {
+        self.try_rename(name, new_name, timestamp)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `change_passphrase` re-wrap the data key under a new passphrase and KDF settings
+    ///
+    /// Entries keep their data key, so only the small key header changes. Call `export` afterwards.
+    pub fn change_passphrase(
+        &mut self,
+        new_passphrase: &str,
+        params: &KdfParams,
+    ) -> Result<(), JsValue> {
+        self.wrap_data_key(new_passphrase, params)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// `params` KDF settings currently protecting the vault
+    pub fn params(&self) -> KdfParams {
+        self.params
+    }
+}
+
+#[cfg(test)]
+fn test_params() -> KdfParams {
+    KdfParams::recommended()
+}
+
+#[test]
+fn vault_roundtrip_test() {
+    let mut vault = Vault::try_create("master pass", &test_params()).unwrap();
+    vault
+        .try_add("github", "totp", b"JBSWY3DPEHPK3PXP", 1_700_000_000)
+        .unwrap();
+    vault
+        .try_add("api", "token", b"sk-123", 1_700_000_100)
+        .unwrap();
+    vault.try_add("empty", "note", b"", 1_700_000_200).unwrap();
+    assert!(vault.try_add("api", "token", b"dup", 1).is_err());
+    assert!(vault.try_add("", "token", b"x", 1).is_err());
+
+    let blob = vault.try_export().unwrap();
+    println!("vault: {}", blob);
+    assert!(blob.starts_with("rtvault$1$argon2id$m=19456,t=2,p=1$"));
+    assert!(!blob.contains("github"));
+
+    let mut opened = Vault::try_unlock(&blob, "master pass").unwrap();
+    assert_eq!(vec!["github", "api", "empty"], opened.names());
+    assert_eq!(b"sk-123".to_vec(), opened.get("api").unwrap());
+    let info = opened.entry("github").unwrap();
+    assert_eq!("totp", info.kind());
+    assert_eq!(1_700_000_000, info.created());
+    assert_eq!(16, info.len());
+    assert!(opened.entry("empty").unwrap().is_empty());
+
+    opened.try_rename("api", "openai", 1_700_000_300).unwrap();
+    assert!(opened.try_rename("openai", "github", 1).is_err());
+    assert!(opened.try_rename("missing", "x", 1).is_err());
+    opened.update("openai", b"sk-456", 1_700_000_400).unwrap();
+    assert!(opened.remove("empty"));
+    assert!(!opened.remove("empty"));
+    assert!(!opened.contains("api"));
+
+    let reopened = Vault::try_unlock(&opened.try_export().unwrap(), "master pass").unwrap();
+    assert_eq!(vec!["github", "openai"], reopened.names());
+    assert_eq!(b"sk-456".to_vec(), reopened.get("openai").unwrap());
+    let info = reopened.entry("openai").unwrap();
+    assert_eq!(1_700_000_100, info.created());
+    assert_eq!(1_700_000_400, info.updated());
+}
+
+#[test]
+fn vault_passphrase_test() {
+    let mut vault = Vault::try_create("old pass", &test_params()).unwrap();
+    vault.try_add("seed", "ed25519", &[7u8; 32], 1).unwrap();
+    let old_blob = vault.try_export().unwrap();
+    assert!(Vault::try_unlock(&old_blob, "wrong").is_err());
+    assert!(Vault::try_create("", &test_params()).is_err());
+
+    let new_params = KdfParams::try_scrypt(15, 8, 1).unwrap();
+    vault.change_passphrase("new pass", &new_params).unwrap();
+    assert_eq!(new_params, vault.params());
+    let new_blob = vault.try_export().unwrap();
+    assert!(new_blob.starts_with("rtvault$1$scrypt$ln=15,r=8,p=1$"));
+    assert!(Vault::try_unlock(&new_blob, "old pass").is_err());
+    let opened = Vault::try_unlock(&new_blob, "new pass").unwrap();
+    assert_eq!(vec![7u8; 32], opened.get("seed").unwrap());
+
+    // the data key is unchanged, so only the key header differs
+    let old = Vault::try_unlock(&old_blob, "old pass").unwrap();
+    assert_eq!(old.data_key.bytes(), opened.data_key.bytes());
+
+    // body from one export cannot be attached to another key header
+    let old_fields: Vec<&str> = old_blob.split('$').collect();
+    let new_fields: Vec<&str> = new_blob.split('$').collect();
+    let mixed = [&new_fields[..6], &old_fields[6..]].concat().join("$");
+    assert!(Vault::try_unlock(&mixed, "new pass").is_err());
+
+    let mut tampered = new_blob.into_bytes();
+    let last = tampered.len() - 2;
+    tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+    assert!(Vault::try_unlock(&String::from_utf8(tampered).unwrap(), "new pass").is_err());
+    assert!(Vault::try_unlock("rtpw$1$x", "new pass").is_err());
+}
+
+#[test]
+fn vault_kdf_limits_test() {
+    // too weak to wrap the data key
+    for weak in [
+        KdfParams::try_argon2id(64, 1, 1).unwrap(),
+        KdfParams::try_argon2id(19 * 1024, 1, 1).unwrap(),
+        KdfParams::try_scrypt(14, 8, 1).unwrap(),
+    ] {
+        assert!(Vault::try_create("master pass", &weak).is_err());
+    }
+    let mut vault = Vault::try_create("master pass", &test_params()).unwrap();
+    let blob = vault.try_export().unwrap();
+    assert!(
+        vault
+            .wrap_data_key("new pass", &KdfParams::try_scrypt(1, 1, 1).unwrap())
+            .is_err()
+    );
+    assert_eq!(test_params(), vault.params());
+
+    // a failed passphrase change leaves an exportable vault that opens with the old passphrase
+    vault.add("api", "token", b"abc", 1).unwrap();
+    assert!(vault.wrap_data_key("", &test_params()).is_err());
+    assert!(
+        vault
+            .wrap_data_key(
+                "new pass",
+                &KdfParams::try_argon2id(19 * 1024, 1, 1).unwrap()
+            )
+            .is_err()
+    );
+    let reopened = Vault::try_unlock(&vault.try_export().unwrap(), "master pass").unwrap();
+    assert_eq!(b"abc".to_vec(), reopened.get("api").unwrap());
+    assert!(Vault::try_unlock(&vault.try_export().unwrap(), "new pass").is_err());
+
+    // recorded parameters above the budget are refused before deriving anything
+    assert!(Vault::try_unlock_with_limit(&blob, "master pass", 19 * 1024).is_ok());
+    let err = Vault::try_unlock_with_limit(&blob, "master pass", 19 * 1024 - 1).err();
+    assert!(err.unwrap().contains("limit"));
+    let expensive = blob.replacen("m=19456,t=2", "m=1048576,t=10", 1);
+    let err = Vault::try_unlock(&expensive, "master pass").err();
+    assert!(err.unwrap().contains("limit"));
+}